@@ -0,0 +1,682 @@
+#![warn(clippy::all)]
+use std::{borrow::Cow, collections::BTreeMap, fmt::Display, marker::PhantomData};
+
+use serde::{
+    Deserialize,
+    de::{self, DeserializeSeed, IntoDeserializer, Visitor},
+};
+use thiserror::Error;
+
+use super::{
+    decoder::{DecodeError, Decoder, Token, TokenKind},
+    value::Value,
+};
+
+#[derive(Debug, Error)]
+pub enum DeserializeError {
+    #[error("error while decoding bencode: {0}")]
+    Decode(#[from] DecodeError),
+    #[error("expected {expected}, got {got} at index {pos}")]
+    UnexpectedType {
+        expected: &'static str,
+        got: TokenKind,
+        pos: usize,
+    },
+    #[error("integer {0} is out of range")]
+    IntOutOfRange(i64),
+    #[error("{0} is not supported by bencode")]
+    Unsupported(&'static str),
+    #[error("{0}")]
+    Custom(String),
+}
+
+impl de::Error for DeserializeError {
+    fn custom<T: Display>(msg: T) -> Self {
+        DeserializeError::Custom(msg.to_string())
+    }
+}
+
+/// Deserializes `T` from a single bencode value, the whole `src` must be consumed.
+/// Byte strings can be borrowed from `src` as `&[u8]`, `&str` or `Cow`.
+pub fn from_bytes<'de, T: Deserialize<'de>>(src: &'de [u8]) -> Result<T, DeserializeError> {
+    let mut de = Deserializer::new(src);
+    let value = T::deserialize(&mut de)?;
+
+    if !de.dec.is_finished() {
+        return Err(DecodeError::TrailingBytes(de.dec.pos()).into());
+    }
+    Ok(value)
+}
+
+/// Deserializes `T` from the value at the start of `src` and returns the
+/// number of bytes it took, for values followed by raw data.
+pub fn from_prefix<'de, T: Deserialize<'de>>(
+    src: &'de [u8],
+) -> Result<(T, usize), DeserializeError> {
+    let mut de = Deserializer::new(src);
+    let value = T::deserialize(&mut de)?;
+    Ok((value, de.dec.pos()))
+}
+
+pub struct Deserializer<'de> {
+    dec: Decoder<'de>,
+}
+
+impl<'de> Deserializer<'de> {
+    pub fn new(src: &'de [u8]) -> Deserializer<'de> {
+        Deserializer {
+            dec: Decoder::new(src),
+        }
+    }
+
+    fn peek(&self) -> Result<Token<'de>, DeserializeError> {
+        Ok(self.dec.peek_token()?.0)
+    }
+
+    fn next(&mut self) -> Result<Token<'de>, DeserializeError> {
+        Ok(self.dec.next_token()?)
+    }
+
+    fn unexpected(&self, expected: &'static str, got: Token<'de>) -> DeserializeError {
+        DeserializeError::UnexpectedType {
+            expected,
+            got: got.into(),
+            pos: self.dec.pos(),
+        }
+    }
+
+    fn next_int(&mut self) -> Result<i64, DeserializeError> {
+        match self.peek()? {
+            Token::Int(i) => {
+                self.next()?;
+                Ok(i)
+            }
+            t => Err(self.unexpected("int", t)),
+        }
+    }
+
+    fn next_bytes(&mut self) -> Result<Cow<'de, [u8]>, DeserializeError> {
+        match self.peek()? {
+            Token::String(_) => match self.next()? {
+                Token::String(s) => Ok(s),
+                _ => unreachable!("token was peeked"),
+            },
+            t => Err(self.unexpected("string", t)),
+        }
+    }
+
+    fn skip_value(&mut self) -> Result<(), DeserializeError> {
+        let mut depth = 0usize;
+        loop {
+            match self.next()? {
+                Token::Int(_) | Token::String(_) => {}
+                Token::BeginDict(_) | Token::BeginList(_) => depth += 1,
+                Token::EndObject(pos) if depth == 0 => {
+                    return Err(DecodeError::UnexpectedObjectClosure(pos).into());
+                }
+                Token::EndObject(_) => depth -= 1,
+            }
+            if depth == 0 {
+                return Ok(());
+            }
+        }
+    }
+
+    fn expect_end(&mut self) -> Result<(), DeserializeError> {
+        match self.next()? {
+            Token::EndObject(_) => Ok(()),
+            t => Err(self.unexpected("end of object", t)),
+        }
+    }
+}
+
+macro_rules! deserialize_int {
+    ($method:ident, $ty:ty, $visit:ident) => {
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+            let i = self.next_int()?;
+            let n = <$ty>::try_from(i).map_err(|_| DeserializeError::IntOutOfRange(i))?;
+            visitor.$visit(n)
+        }
+    };
+}
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
+    type Error = DeserializeError;
+
+    /// Byte strings hold hashes and ids as they are.
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.peek()? {
+            Token::Int(_) => self.deserialize_i64(visitor),
+            Token::String(_) => self.deserialize_bytes(visitor),
+            Token::BeginList(_) => self.deserialize_seq(visitor),
+            Token::BeginDict(_) => self.deserialize_map(visitor),
+            Token::EndObject(pos) => Err(DecodeError::UnexpectedObjectClosure(pos).into()),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.next_int()? {
+            0 => visitor.visit_bool(false),
+            1 => visitor.visit_bool(true),
+            i => Err(DeserializeError::IntOutOfRange(i)),
+        }
+    }
+
+    deserialize_int!(deserialize_i8, i8, visit_i8);
+    deserialize_int!(deserialize_i16, i16, visit_i16);
+    deserialize_int!(deserialize_i32, i32, visit_i32);
+    deserialize_int!(deserialize_i64, i64, visit_i64);
+    deserialize_int!(deserialize_u8, u8, visit_u8);
+    deserialize_int!(deserialize_u16, u16, visit_u16);
+    deserialize_int!(deserialize_u32, u32, visit_u32);
+    deserialize_int!(deserialize_u64, u64, visit_u64);
+
+    fn deserialize_f32<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+        Err(DeserializeError::Unsupported("f32"))
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+        Err(DeserializeError::Unsupported("f64"))
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.next_bytes()? {
+            Cow::Borrowed(b) => visitor.visit_borrowed_str(
+                std::str::from_utf8(b).map_err(|e| DeserializeError::Custom(e.to_string()))?,
+            ),
+            Cow::Owned(b) => visitor.visit_string(
+                String::from_utf8(b).map_err(|e| DeserializeError::Custom(e.to_string()))?,
+            ),
+        }
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.next_bytes()? {
+            Cow::Borrowed(b) => visitor.visit_borrowed_bytes(b),
+            Cow::Owned(b) => visitor.visit_byte_buf(b),
+        }
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_bytes(visitor)
+    }
+
+    /// Bencode has no null, a present value is always `Some`.
+    /// Missing struct fields become `None` on their own.
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+        Err(DeserializeError::Unsupported("unit"))
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// Byte strings are also accepted as sequences, so `[u8; 20]` and
+    /// `Vec<u8>` can be read from a string like an info hash.
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.peek()? {
+            Token::BeginList(_) => {
+                self.next()?;
+                let mut access = ListAccess {
+                    de: self,
+                    finished: false,
+                };
+                let value = visitor.visit_seq(&mut access)?;
+                if !access.finished {
+                    self.expect_end()?;
+                }
+                Ok(value)
+            }
+            Token::String(_) => {
+                let bytes = self.next_bytes()?;
+                visitor.visit_seq(de::value::SeqDeserializer::new(bytes.iter().copied()))
+            }
+            t => Err(self.unexpected("list", t)),
+        }
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.peek()? {
+            Token::BeginDict(_) => {
+                self.next()?;
+                let mut access = DictAccess {
+                    de: self,
+                    finished: false,
+                };
+                let value = visitor.visit_map(&mut access)?;
+                if !access.finished {
+                    self.expect_end()?;
+                }
+                Ok(value)
+            }
+            t => Err(self.unexpected("dict", t)),
+        }
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_map(visitor)
+    }
+
+    /// Unit variants are byte strings, other variants are dicts with a single key.
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.peek()? {
+            Token::String(_) => {
+                let variant = self.next_bytes()?;
+                let variant = String::from_utf8(variant.into_owned())
+                    .map_err(|e| DeserializeError::Custom(e.to_string()))?;
+                visitor.visit_enum(variant.into_deserializer())
+            }
+            Token::BeginDict(_) => {
+                self.next()?;
+                let value = visitor.visit_enum(VariantAccess { de: self })?;
+                self.expect_end()?;
+                Ok(value)
+            }
+            t => Err(self.unexpected("string or dict", t)),
+        }
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.next_bytes()? {
+            Cow::Borrowed(b) => match std::str::from_utf8(b) {
+                Ok(s) => visitor.visit_borrowed_str(s),
+                Err(_) => visitor.visit_borrowed_bytes(b),
+            },
+            Cow::Owned(b) => visitor.visit_byte_buf(b),
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.skip_value()?;
+        visitor.visit_unit()
+    }
+}
+
+struct ListAccess<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    finished: bool,
+}
+
+impl<'de> de::SeqAccess<'de> for ListAccess<'_, 'de> {
+    type Error = DeserializeError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Self::Error> {
+        if self.finished {
+            return Ok(None);
+        }
+        if let Token::EndObject(_) = self.de.peek()? {
+            self.de.next()?;
+            self.finished = true;
+            return Ok(None);
+        }
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+}
+
+struct DictAccess<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    finished: bool,
+}
+
+impl<'de> de::MapAccess<'de> for DictAccess<'_, 'de> {
+    type Error = DeserializeError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        if self.finished {
+            return Ok(None);
+        }
+        match self.de.peek()? {
+            Token::EndObject(_) => {
+                self.de.next()?;
+                self.finished = true;
+                Ok(None)
+            }
+            Token::String(_) => seed.deserialize(&mut *self.de).map(Some),
+            _ => Err(DecodeError::KeyNotString(self.de.dec.pos()).into()),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        seed.deserialize(&mut *self.de)
+    }
+}
+
+struct VariantAccess<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+}
+
+impl<'de> de::EnumAccess<'de> for VariantAccess<'_, 'de> {
+    type Error = DeserializeError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self::Variant), Self::Error> {
+        let variant = seed.deserialize(&mut *self.de)?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for VariantAccess<'_, 'de> {
+    type Error = DeserializeError;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        Err(DeserializeError::Unsupported(
+            "unit variant inside of a dict",
+        ))
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, Self::Error> {
+        seed.deserialize(&mut *self.de)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        de::Deserializer::deserialize_seq(&mut *self.de, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        de::Deserializer::deserialize_map(&mut *self.de, visitor)
+    }
+}
+
+/// Lets `Value` catch whatever is left, e.g. under `#[serde(flatten)]`,
+/// or stand in for a field of more than one type with `#[serde(borrow)]`.
+impl<'de: 'a, 'a> Deserialize<'de> for Value<'a> {
+    fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ValueVisitor(PhantomData))
+    }
+}
+
+struct ValueVisitor<'a>(PhantomData<Value<'a>>);
+
+impl<'de: 'a, 'a> Visitor<'de> for ValueVisitor<'a> {
+    type Value = Value<'a>;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "a bencode value")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Self::Value, E> {
+        Ok(Value::Int(v as i64))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        Ok(Value::Int(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        i64::try_from(v)
+            .map(Value::Int)
+            .map_err(|_| E::custom(format!("integer {v} is out of range")))
+    }
+
+    fn visit_borrowed_bytes<E: de::Error>(self, v: &'de [u8]) -> Result<Self::Value, E> {
+        Ok(Value::Bytes(Cow::Borrowed(v)))
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        Ok(Value::Bytes(Cow::Owned(v.to_vec())))
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+        Ok(Value::Bytes(Cow::Owned(v)))
+    }
+
+    fn visit_borrowed_str<E: de::Error>(self, v: &'de str) -> Result<Self::Value, E> {
+        Ok(Value::Bytes(Cow::Borrowed(v.as_bytes())))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(Value::Bytes(Cow::Owned(v.as_bytes().to_vec())))
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Self::Value, E> {
+        Ok(Value::Bytes(Cow::Owned(v.into_bytes())))
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut list = Vec::new();
+        while let Some(v) = seq.next_element()? {
+            list.push(v);
+        }
+        Ok(Value::List(list))
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut dict = BTreeMap::new();
+        while let Some(key) = map.next_key::<Value<'a>>()? {
+            let key = match key {
+                Value::Bytes(key) => key,
+                other => {
+                    return Err(de::Error::custom(format!(
+                        "dict key must be a string, got {}",
+                        other.kind()
+                    )));
+                }
+            };
+            dict.insert(key, map.next_value()?);
+        }
+        Ok(Value::Dict(dict))
+    }
+}
+
+#[cfg(test)]
+mod test_de {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Peer<'a> {
+        ip: &'a str,
+        port: u16,
+        #[serde(rename = "peer id", with = "serde_bytes")]
+        peer_id: &'a [u8],
+    }
+
+    #[derive(Deserialize, Debug)]
+    struct Announce<'a> {
+        interval: u32,
+        #[serde(rename = "failure reason")]
+        failure_reason: Option<String>,
+        #[serde(borrow)]
+        peers: Vec<Peer<'a>>,
+    }
+
+    #[test]
+    fn prefix_leaves_trailing_bytes() {
+        let data = b"d8:intervali1800e5:peerslee<raw data>";
+        let (announce, len): (Announce, _) = from_prefix(data).unwrap();
+
+        assert_eq!(announce.interval, 1800);
+        assert_eq!(&data[len..], b"<raw data>");
+    }
+
+    #[test]
+    fn valid_struct_with_borrowed_fields() {
+        let data = b"d8:intervali1800e5:peersld2:ip9:127.0.0.17:peer id3:abc4:porti6881eeee";
+        let announce: Announce = from_bytes(data).unwrap();
+
+        assert_eq!(announce.interval, 1800);
+        assert_eq!(announce.failure_reason, None);
+        assert_eq!(
+            announce.peers,
+            vec![Peer {
+                ip: "127.0.0.1",
+                port: 6881,
+                peer_id: b"abc",
+            }]
+        );
+    }
+
+    #[test]
+    fn byte_strings_and_cows() {
+        #[derive(Deserialize)]
+        struct Ping<'a> {
+            #[serde(with = "serde_bytes")]
+            id: [u8; 4],
+            #[serde(borrow)]
+            token: Cow<'a, [u8]>,
+            #[serde(with = "serde_bytes")]
+            owned: Vec<u8>,
+        }
+
+        let ping: Ping = from_bytes(b"d2:id4:abcd5:owned2:xy5:token3:toke").unwrap();
+
+        assert_eq!(&ping.id, b"abcd");
+        assert_eq!(&*ping.token, b"tok");
+        assert_eq!(ping.owned, b"xy");
+    }
+
+    #[test]
+    fn unknown_keys_are_captured_with_flatten() {
+        #[derive(Deserialize)]
+        struct Meta<'a> {
+            announce: String,
+            #[serde(flatten, borrow)]
+            extra: BTreeMap<String, Value<'a>>,
+        }
+
+        let meta: Meta = from_bytes(b"d8:announce3:url7:comment2:hi8:url-listl1:a1:bee").unwrap();
+
+        assert_eq!(meta.announce, "url");
+        assert_eq!(meta.extra["comment"].as_str(), Some("hi"));
+        assert_eq!(meta.extra["url-list"].index(1).unwrap().as_str(), Some("b"));
+    }
+
+    #[test]
+    fn unknown_keys_are_skipped() {
+        #[derive(Deserialize)]
+        struct Only {
+            a: i64,
+        }
+
+        let only: Only = from_bytes(b"d1:ai1e1:bld1:xli2eeee1:c3:fooe").unwrap();
+        assert_eq!(only.a, 1);
+    }
+
+    #[test]
+    fn enums() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        #[serde(rename_all = "lowercase")]
+        enum Kind {
+            Query,
+            Response(i64),
+        }
+
+        assert_eq!(from_bytes::<Kind>(b"5:query").unwrap(), Kind::Query);
+        assert_eq!(
+            from_bytes::<Kind>(b"d8:responsei3ee").unwrap(),
+            Kind::Response(3)
+        );
+    }
+
+    #[test]
+    fn error_on_wrong_type() {
+        #[derive(Deserialize, Debug)]
+        #[allow(dead_code)]
+        struct Only {
+            a: i64,
+        }
+
+        assert!(matches!(
+            from_bytes::<Only>(b"d1:a1:xe").unwrap_err(),
+            DeserializeError::UnexpectedType {
+                expected: "int",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn error_on_int_out_of_range() {
+        assert!(matches!(
+            from_bytes::<u8>(b"i256e").unwrap_err(),
+            DeserializeError::IntOutOfRange(256)
+        ));
+    }
+
+    #[test]
+    fn error_on_trailing_bytes() {
+        assert!(matches!(
+            from_bytes::<i64>(b"i1ei2e").unwrap_err(),
+            DeserializeError::Decode(DecodeError::TrailingBytes(3))
+        ));
+    }
+}
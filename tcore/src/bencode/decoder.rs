@@ -49,6 +49,110 @@ impl<'a> From<Token<'a>> for TokenKind {
 pub struct Decoder<'a> {
     src: &'a [u8],
     pos: usize,
+    token_pos: usize,
+    options: DecoderOptions,
+    frames: Vec<Frame<'a>>,
+    tokens: usize,
+}
+
+#[derive(Default, Debug, Clone, Copy)]
+pub struct DecoderOptions {
+    /// Reject anything that is not canonical bencode, see [`CanonicalRule`].
+    pub strict: bool,
+    pub limits: DecodeLimits,
+}
+
+/// Bounds for decoding untrusted input. The defaults are generous enough
+/// for any sane .torrent file, but stop hostile input early.
+#[derive(Debug, Clone, Copy)]
+pub struct DecodeLimits {
+    pub max_depth: usize,
+    pub max_string_len: usize,
+    pub max_tokens: usize,
+    /// Max number of elements in a list or of entries in a dict.
+    pub max_container_len: usize,
+    /// Only checked by the torrent builder, the decoder does not know about files.
+    pub max_files: usize,
+}
+
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        DecodeLimits {
+            max_depth: 256,
+            max_string_len: 128 * 1024 * 1024,
+            max_tokens: 16 * 1024 * 1024,
+            max_container_len: 4 * 1024 * 1024,
+            max_files: 1024 * 1024,
+        }
+    }
+}
+
+impl DecodeLimits {
+    pub fn unlimited() -> DecodeLimits {
+        DecodeLimits {
+            max_depth: usize::MAX,
+            max_string_len: usize::MAX,
+            max_tokens: usize::MAX,
+            max_container_len: usize::MAX,
+            max_files: usize::MAX,
+        }
+    }
+}
+
+/// A limit that was exceeded, with its configured maximum.
+#[derive(Debug, PartialEq)]
+pub enum Limit {
+    Depth(usize),
+    StringLength(usize),
+    Tokens(usize),
+    ContainerLength(usize),
+    Files(usize),
+}
+
+impl Display for Limit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Self::Depth(max) => write!(f, "nesting depth of {max}"),
+            Self::StringLength(max) => write!(f, "string length of {max}"),
+            Self::Tokens(max) => write!(f, "total of {max} tokens"),
+            Self::ContainerLength(max) => write!(f, "{max} list or dict entries"),
+            Self::Files(max) => write!(f, "{max} files"),
+        }
+    }
+}
+
+enum Frame<'a> {
+    List {
+        entries: usize,
+    },
+    Dict {
+        last_key: Option<&'a [u8]>,
+        expect_key: bool,
+        entries: usize,
+    },
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CanonicalRule {
+    UnsortedKeys,
+    DuplicateKey,
+    NegativeZero,
+    LeadingZeroInInt,
+    LeadingZeroInLength,
+    PlusSign,
+}
+
+impl Display for CanonicalRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Self::UnsortedKeys => write!(f, "dict keys must be sorted"),
+            Self::DuplicateKey => write!(f, "dict keys must be unique"),
+            Self::NegativeZero => write!(f, "int must not be negative zero"),
+            Self::LeadingZeroInInt => write!(f, "int must not have leading zeros"),
+            Self::LeadingZeroInLength => write!(f, "string length must not have leading zeros"),
+            Self::PlusSign => write!(f, "int must not have a plus sign"),
+        }
+    }
 }
 
 #[derive(Debug, Error)]
@@ -67,21 +171,157 @@ pub enum DecodeError {
     WrongSyntax,
     #[error("missing colon in string")]
     MissingColonInString,
+    #[error("dict key at index {0} is not a string")]
+    KeyNotString(usize),
+    #[error("duplicate dict key at index {0}")]
+    DuplicateKey(usize),
+    #[error("unexpected object closure at index {0}")]
+    UnexpectedObjectClosure(usize),
+    #[error("trailing bytes after the value at index {0}")]
+    TrailingBytes(usize),
+    #[error("non-canonical bencode at index {offset}: {rule}")]
+    NonCanonical { offset: usize, rule: CanonicalRule },
+    #[error("limit exceeded at index {offset}: more than {limit}")]
+    LimitExceeded { offset: usize, limit: Limit },
 }
 
 impl<'a> Decoder<'a> {
     pub fn new(src: &'a [u8]) -> Decoder<'a> {
-        Decoder { src, pos: 0 }
+        Decoder::with_options(src, DecoderOptions::default())
+    }
+
+    pub fn with_options(src: &'a [u8], options: DecoderOptions) -> Decoder<'a> {
+        Decoder {
+            src,
+            pos: 0,
+            token_pos: 0,
+            options,
+            frames: Vec::new(),
+            tokens: 0,
+        }
     }
 
     pub fn next_token(&mut self) -> Result<Token<'a>, DecodeError> {
         let (token, size) = self.peek_token()?;
+        self.track(&token)?;
+        self.token_pos = self.pos;
         self.step_forward_unchecked(size);
         Ok(token)
     }
 
+    /// Position of the token last returned by [`Decoder::next_token`].
+    pub fn token_pos(&self) -> usize {
+        self.token_pos
+    }
+
+    /// Number of lists and dicts that are open at the moment.
+    pub fn depth(&self) -> usize {
+        self.frames.len()
+    }
+
+    fn track(&mut self, token: &Token<'a>) -> Result<(), DecodeError> {
+        let strict = self.options.strict;
+        let limits = self.options.limits;
+
+        self.tokens += 1;
+        if self.tokens > limits.max_tokens {
+            return Err(self.limit_exceeded(Limit::Tokens(limits.max_tokens)));
+        }
+
+        let closing = matches!(token, Token::EndObject(_));
+        match self.frames.last_mut() {
+            Some(Frame::List { entries }) if !closing => {
+                *entries += 1;
+                if *entries > limits.max_container_len {
+                    return Err(
+                        self.limit_exceeded(Limit::ContainerLength(limits.max_container_len))
+                    );
+                }
+            }
+
+            Some(Frame::Dict {
+                last_key,
+                expect_key,
+                entries,
+            }) => {
+                if *expect_key && !closing {
+                    *entries += 1;
+                    if *entries > limits.max_container_len {
+                        return Err(
+                            self.limit_exceeded(Limit::ContainerLength(limits.max_container_len))
+                        );
+                    }
+                }
+
+                match token {
+                    Token::String(key) if *expect_key && strict => {
+                        // canonical keys are always borrowed straight from src
+                        let key: &'a [u8] = match key {
+                            Cow::Borrowed(key) => key,
+                            Cow::Owned(_) => unreachable!("decoder tokens borrow from src"),
+                        };
+                        if let Some(prev) = last_key {
+                            if *prev == key {
+                                return Err(self.non_canonical(CanonicalRule::DuplicateKey));
+                            }
+                            if *prev > key {
+                                return Err(self.non_canonical(CanonicalRule::UnsortedKeys));
+                            }
+                        }
+                        *last_key = Some(key);
+                        *expect_key = false;
+                    }
+                    Token::EndObject(pos) if strict && !*expect_key => {
+                        return Err(DecodeError::UnexpectedObjectClosure(*pos));
+                    }
+                    Token::EndObject(_) => {}
+                    _ if *expect_key && strict => {
+                        return Err(DecodeError::KeyNotString(self.pos));
+                    }
+                    _ => *expect_key = !*expect_key,
+                }
+            }
+
+            _ => {}
+        }
+
+        match token {
+            Token::BeginList(_) | Token::BeginDict(_) if self.frames.len() >= limits.max_depth => {
+                return Err(self.limit_exceeded(Limit::Depth(limits.max_depth)));
+            }
+            Token::BeginList(_) => self.frames.push(Frame::List { entries: 0 }),
+            Token::BeginDict(_) => self.frames.push(Frame::Dict {
+                last_key: None,
+                expect_key: true,
+                entries: 0,
+            }),
+            Token::EndObject(pos) => {
+                let closed = self.frames.pop();
+                if closed.is_none() && strict {
+                    return Err(DecodeError::UnexpectedObjectClosure(*pos));
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn limit_exceeded(&self, limit: Limit) -> DecodeError {
+        DecodeError::LimitExceeded {
+            offset: self.pos,
+            limit,
+        }
+    }
+
+    fn non_canonical(&self, rule: CanonicalRule) -> DecodeError {
+        DecodeError::NonCanonical {
+            offset: self.pos,
+            rule,
+        }
+    }
+
     pub fn peek_token(&self) -> Result<(Token<'a>, usize), DecodeError> {
-        match self.current_byte() {
+        match self.current_byte()? {
             b'i' => self.give_int_token(),
             b'0'..=b'9' => self.give_string_token(),
             b'l' => Ok((Token::BeginList(self.pos), 1)),
@@ -103,6 +343,14 @@ impl<'a> Decoder<'a> {
         self.pos +=steps;
     }
 
+    pub fn pos(&self) -> usize {
+        self.pos
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.pos >= self.src.len()
+    }
+
     fn give_int_token(&self) -> Result<(Token<'a>, usize), DecodeError> {
         let e_pos = match self.src[self.pos..].iter().position(|x| *x == b'e') {
             Some(pos) => pos,
@@ -113,6 +361,21 @@ impl<'a> Decoder<'a> {
             return Err(DecodeError::TokenTooLarge);
         }
 
+        let digits = &self.src[self.pos + 1..self.pos + e_pos];
+        if digits.is_empty() {
+            return Err(DecodeError::WrongSyntax);
+        }
+        if self.options.strict {
+            match digits {
+                [b'+', ..] => return Err(self.non_canonical(CanonicalRule::PlusSign)),
+                [b'-', b'0'] => return Err(self.non_canonical(CanonicalRule::NegativeZero)),
+                [b'0', _, ..] | [b'-', b'0', _, ..] => {
+                    return Err(self.non_canonical(CanonicalRule::LeadingZeroInInt));
+                }
+                _ => {}
+            }
+        }
+
         let (maybe_n, used) =
             i64::from_radix_10_signed_checked(&self.src[self.pos + 1..self.pos + 1 + e_pos]);
 
@@ -137,6 +400,10 @@ impl<'a> Decoder<'a> {
             return Err(DecodeError::TokenTooLarge);
         }
 
+        if self.options.strict && col_pos > 1 && self.src[self.pos] == b'0' {
+            return Err(self.non_canonical(CanonicalRule::LeadingZeroInLength));
+        }
+
         let (maybe_len, used) =
             u64::from_radix_10_signed_checked(&self.src[self.pos..self.pos + col_pos]);
 
@@ -152,6 +419,11 @@ impl<'a> Decoder<'a> {
             }
         } as usize;
 
+        let max = self.options.limits.max_string_len;
+        if length > max {
+            return Err(self.limit_exceeded(Limit::StringLength(max)));
+        }
+
         let have = self.src[self.pos + col_pos + 1..].len();
         if have < length {
             return Err(DecodeError::UnfinishedString(self.pos, length, have));
@@ -164,9 +436,35 @@ impl<'a> Decoder<'a> {
         Ok((token, col_pos + length + 1))
     }
 
-    fn current_byte(&self) -> u8 {
-        self.src[self.pos]
+    fn current_byte(&self) -> Result<u8, DecodeError> {
+        self.src
+            .get(self.pos)
+            .copied()
+            .ok_or(DecodeError::PosOutOfBounds)
+    }
+}
+
+/// Checks that `src` holds exactly one value in canonical bencode.
+pub fn validate_canonical(src: &[u8]) -> Result<(), DecodeError> {
+    let mut dec = Decoder::with_options(
+        src,
+        DecoderOptions {
+            strict: true,
+            ..Default::default()
+        },
+    );
+
+    loop {
+        dec.next_token()?;
+        if dec.depth() == 0 {
+            break;
+        }
     }
+
+    if !dec.is_finished() {
+        return Err(DecodeError::TrailingBytes(dec.pos()));
+    }
+    Ok(())
 }
 
 #[cfg(test)]
@@ -332,6 +630,18 @@ mod test_decode {
         ));
     }
 
+    #[test]
+    fn error_on_end_of_input() {
+        let mut dec = Decoder::new(b"i1e");
+        dec.next_token().unwrap();
+
+        assert!(dec.is_finished());
+        assert!(matches!(
+            dec.next_token().unwrap_err(),
+            DecodeError::PosOutOfBounds
+        ));
+    }
+
     #[test]
     fn error_wrong_int_syntax() {
         let mut dec = Decoder::new(b"i4xe");
@@ -351,4 +661,154 @@ mod test_decode {
             DecodeError::WrongSyntax
         ));
     }
+
+    fn strict_error(input: &[u8]) -> (usize, CanonicalRule) {
+        match validate_canonical(input).unwrap_err() {
+            DecodeError::NonCanonical { offset, rule } => (offset, rule),
+            e => panic!("unexpected error: {e}"),
+        }
+    }
+
+    #[test]
+    fn canonical_input_is_valid() {
+        validate_canonical(b"d1:ai0e1:bli-1e0:e1:cdee").unwrap();
+    }
+
+    #[test]
+    fn lenient_mode_accepts_non_canonical_input() {
+        let mut dec = Decoder::new(b"d1:bi01e1:ai-0ee");
+
+        for _ in 0..6 {
+            dec.next_token().unwrap();
+        }
+        assert!(dec.is_finished());
+    }
+
+    #[test]
+    fn error_on_unsorted_keys() {
+        assert_eq!(
+            strict_error(b"d1:bi1e1:ai2ee"),
+            (7, CanonicalRule::UnsortedKeys)
+        );
+    }
+
+    #[test]
+    fn error_on_duplicate_keys() {
+        assert_eq!(
+            strict_error(b"d1:ai1e1:ai2ee"),
+            (7, CanonicalRule::DuplicateKey)
+        );
+    }
+
+    #[test]
+    fn error_on_non_canonical_ints() {
+        assert_eq!(strict_error(b"li-0ee"), (1, CanonicalRule::NegativeZero));
+        assert_eq!(strict_error(b"i03e"), (0, CanonicalRule::LeadingZeroInInt));
+        assert_eq!(strict_error(b"i-05e"), (0, CanonicalRule::LeadingZeroInInt));
+        assert_eq!(strict_error(b"i+3e"), (0, CanonicalRule::PlusSign));
+    }
+
+    #[test]
+    fn error_on_leading_zero_in_length() {
+        assert_eq!(
+            strict_error(b"l03:abce"),
+            (1, CanonicalRule::LeadingZeroInLength)
+        );
+    }
+
+    #[test]
+    fn error_on_empty_int() {
+        assert!(matches!(
+            Decoder::new(b"ie").next_token().unwrap_err(),
+            DecodeError::WrongSyntax
+        ));
+    }
+
+    #[test]
+    fn strict_error_on_non_string_key() {
+        assert!(matches!(
+            validate_canonical(b"di1ei2ee").unwrap_err(),
+            DecodeError::KeyNotString(1)
+        ));
+    }
+
+    #[test]
+    fn strict_error_on_trailing_bytes() {
+        assert!(matches!(
+            validate_canonical(b"i1ei2e").unwrap_err(),
+            DecodeError::TrailingBytes(3)
+        ));
+    }
+
+    fn decode_all(input: &[u8], limits: DecodeLimits) -> Result<(), DecodeError> {
+        let mut dec = Decoder::with_options(
+            input,
+            DecoderOptions {
+                limits,
+                ..Default::default()
+            },
+        );
+        while !dec.is_finished() {
+            dec.next_token()?;
+        }
+        Ok(())
+    }
+
+    fn limit_error(input: &[u8], limits: DecodeLimits) -> (usize, Limit) {
+        match decode_all(input, limits).unwrap_err() {
+            DecodeError::LimitExceeded { offset, limit } => (offset, limit),
+            e => panic!("unexpected error: {e}"),
+        }
+    }
+
+    #[test]
+    fn error_on_too_deep_nesting() {
+        let limits = DecodeLimits {
+            max_depth: 2,
+            ..DecodeLimits::unlimited()
+        };
+
+        decode_all(b"llee", limits).unwrap();
+        assert_eq!(limit_error(b"lllee", limits), (2, Limit::Depth(2)));
+    }
+
+    #[test]
+    fn error_on_too_long_string() {
+        let limits = DecodeLimits {
+            max_string_len: 3,
+            ..DecodeLimits::unlimited()
+        };
+
+        decode_all(b"3:abc", limits).unwrap();
+        // the length is checked before the string itself arrives
+        assert_eq!(
+            limit_error(b"3:abc100000:", limits),
+            (5, Limit::StringLength(3))
+        );
+    }
+
+    #[test]
+    fn error_on_too_many_tokens() {
+        let limits = DecodeLimits {
+            max_tokens: 3,
+            ..DecodeLimits::unlimited()
+        };
+
+        decode_all(b"li1ee", limits).unwrap();
+        assert_eq!(limit_error(b"li1ei2ee", limits), (7, Limit::Tokens(3)));
+    }
+
+    #[test]
+    fn error_on_too_many_entries() {
+        let limits = DecodeLimits {
+            max_container_len: 2,
+            ..DecodeLimits::unlimited()
+        };
+
+        decode_all(b"li1eli2ei3eee", limits).unwrap();
+        assert_eq!(
+            limit_error(b"d1:ai1e1:bi2e1:ci3ee", limits),
+            (13, Limit::ContainerLength(2))
+        );
+    }
 }
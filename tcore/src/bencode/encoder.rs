@@ -0,0 +1,259 @@
+#![warn(clippy::all)]
+use std::io::Write;
+
+use thiserror::Error;
+
+use super::decoder::Token;
+
+pub struct Encoder<W: Write> {
+    out: W,
+    stack: Vec<Frame>,
+}
+
+enum Frame {
+    List,
+    Dict {
+        last_key: Option<Vec<u8>>,
+        expect_key: bool,
+    },
+}
+
+#[derive(Debug, Error)]
+pub enum EncodeError {
+    #[error("error writing bencode: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("dict key expected to be a string")]
+    ExpectedKey,
+    #[error("dict keys must be unique and sorted, got {got:?} after {prev:?}")]
+    UnsortedKey { prev: Vec<u8>, got: Vec<u8> },
+    #[error("dict key has no value")]
+    MissingValue,
+    #[error("unexpected object closure")]
+    UnexpectedObjectClosure,
+    #[error("{0} objects were left unclosed")]
+    UnclosedObjects(usize),
+}
+
+impl<W: Write> Encoder<W> {
+    pub fn new(out: W) -> Encoder<W> {
+        Encoder {
+            out,
+            stack: Vec::new(),
+        }
+    }
+
+    pub fn int(&mut self, n: i64) -> Result<(), EncodeError> {
+        self.before_value()?;
+        write!(self.out, "i{n}e")?;
+        Ok(())
+    }
+
+    pub fn uint(&mut self, n: u64) -> Result<(), EncodeError> {
+        self.before_value()?;
+        write!(self.out, "i{n}e")?;
+        Ok(())
+    }
+
+    /// Writes a byte string. Inside of a dict it is treated as a key
+    /// or as a value, depending on the position.
+    pub fn bytes(&mut self, b: &[u8]) -> Result<(), EncodeError> {
+        if let Some(Frame::Dict {
+            last_key,
+            expect_key: expect_key @ true,
+        }) = self.stack.last_mut()
+        {
+            if let Some(prev) = last_key
+                && prev.as_slice() >= b
+            {
+                return Err(EncodeError::UnsortedKey {
+                    prev: prev.clone(),
+                    got: b.to_vec(),
+                });
+            }
+            *last_key = Some(b.to_vec());
+            *expect_key = false;
+        } else {
+            self.before_value()?;
+        }
+
+        write_bytes(&mut self.out, b)?;
+        Ok(())
+    }
+
+    pub fn str(&mut self, s: &str) -> Result<(), EncodeError> {
+        self.bytes(s.as_bytes())
+    }
+
+    pub fn begin_list(&mut self) -> Result<(), EncodeError> {
+        self.before_value()?;
+        self.out.write_all(b"l")?;
+        self.stack.push(Frame::List);
+        Ok(())
+    }
+
+    pub fn begin_dict(&mut self) -> Result<(), EncodeError> {
+        self.before_value()?;
+        self.out.write_all(b"d")?;
+        self.stack.push(Frame::Dict {
+            last_key: None,
+            expect_key: true,
+        });
+        Ok(())
+    }
+
+    pub fn end(&mut self) -> Result<(), EncodeError> {
+        match self.stack.pop() {
+            None => return Err(EncodeError::UnexpectedObjectClosure),
+            Some(Frame::Dict {
+                expect_key: false, ..
+            }) => return Err(EncodeError::MissingValue),
+            Some(_) => {}
+        }
+        self.out.write_all(b"e")?;
+        Ok(())
+    }
+
+    /// Writes a token produced by [`super::decoder::Decoder`].
+    pub fn token(&mut self, token: &Token) -> Result<(), EncodeError> {
+        match token {
+            Token::Int(n) => self.int(*n),
+            Token::String(s) => self.bytes(s),
+            Token::BeginDict(_) => self.begin_dict(),
+            Token::BeginList(_) => self.begin_list(),
+            Token::EndObject(_) => self.end(),
+        }
+    }
+
+    pub fn into_inner(self) -> Result<W, EncodeError> {
+        if !self.stack.is_empty() {
+            return Err(EncodeError::UnclosedObjects(self.stack.len()));
+        }
+        Ok(self.out)
+    }
+
+    fn before_value(&mut self) -> Result<(), EncodeError> {
+        if let Some(Frame::Dict { expect_key, .. }) = self.stack.last_mut() {
+            if *expect_key {
+                return Err(EncodeError::ExpectedKey);
+            }
+            *expect_key = true;
+        }
+        Ok(())
+    }
+}
+
+pub(super) fn write_bytes<W: Write>(out: &mut W, b: &[u8]) -> std::io::Result<()> {
+    write!(out, "{}:", b.len())?;
+    out.write_all(b)
+}
+
+#[cfg(test)]
+mod test_encode {
+    use super::*;
+    use crate::bencode::decoder::Decoder;
+
+    fn encode(f: impl FnOnce(&mut Encoder<Vec<u8>>) -> Result<(), EncodeError>) -> Vec<u8> {
+        let mut enc = Encoder::new(Vec::new());
+        f(&mut enc).unwrap();
+        enc.into_inner().unwrap()
+    }
+
+    #[test]
+    fn valid_ints() {
+        assert_eq!(encode(|e| e.int(42)), b"i42e");
+        assert_eq!(encode(|e| e.int(0)), b"i0e");
+        assert_eq!(encode(|e| e.int(-7)), b"i-7e");
+        assert_eq!(encode(|e| e.uint(u64::MAX)), b"i18446744073709551615e");
+    }
+
+    #[test]
+    fn valid_strings() {
+        assert_eq!(encode(|e| e.str("spam")), b"4:spam");
+        assert_eq!(encode(|e| e.bytes(b"")), b"0:");
+    }
+
+    #[test]
+    fn valid_nested_objects() {
+        let out = encode(|e| {
+            e.begin_dict()?;
+            e.str("a")?;
+            e.begin_list()?;
+            e.int(1)?;
+            e.str("b")?;
+            e.end()?;
+            e.str("b")?;
+            e.begin_dict()?;
+            e.end()?;
+            e.end()
+        });
+
+        assert_eq!(out, b"d1:ali1e1:be1:bdee");
+    }
+
+    #[test]
+    fn error_on_unsorted_keys() {
+        let mut enc = Encoder::new(Vec::new());
+        enc.begin_dict().unwrap();
+        enc.str("b").unwrap();
+        enc.int(1).unwrap();
+
+        assert!(matches!(
+            enc.str("a").unwrap_err(),
+            EncodeError::UnsortedKey { .. }
+        ));
+    }
+
+    #[test]
+    fn error_on_duplicate_keys() {
+        let mut enc = Encoder::new(Vec::new());
+        enc.begin_dict().unwrap();
+        enc.str("a").unwrap();
+        enc.int(1).unwrap();
+
+        assert!(matches!(
+            enc.str("a").unwrap_err(),
+            EncodeError::UnsortedKey { .. }
+        ));
+    }
+
+    #[test]
+    fn error_on_non_string_key() {
+        let mut enc = Encoder::new(Vec::new());
+        enc.begin_dict().unwrap();
+
+        assert!(matches!(enc.int(1).unwrap_err(), EncodeError::ExpectedKey));
+    }
+
+    #[test]
+    fn error_on_key_without_value() {
+        let mut enc = Encoder::new(Vec::new());
+        enc.begin_dict().unwrap();
+        enc.str("a").unwrap();
+
+        assert!(matches!(enc.end().unwrap_err(), EncodeError::MissingValue));
+    }
+
+    #[test]
+    fn error_on_unclosed_objects() {
+        let mut enc = Encoder::new(Vec::new());
+        enc.begin_list().unwrap();
+
+        assert!(matches!(
+            enc.into_inner().unwrap_err(),
+            EncodeError::UnclosedObjects(1)
+        ));
+    }
+
+    #[test]
+    fn decoder_tokens_roundtrip() {
+        let input = b"d3:bar4:spam3:fooli42eee";
+        let mut dec = Decoder::new(input);
+        let mut enc = Encoder::new(Vec::new());
+
+        for _ in 0..8 {
+            enc.token(&dec.next_token().unwrap()).unwrap();
+        }
+
+        assert_eq!(enc.into_inner().unwrap(), input);
+    }
+}
@@ -0,0 +1,93 @@
+#![warn(clippy::all)]
+use std::fmt::Display;
+
+/// How many bytes around the offset end up in an excerpt.
+const CONTEXT: usize = 8;
+
+/// A few bytes of the input around an offset, shown as hex and ASCII
+/// with the byte at the offset in brackets. Offset 11 of `d6:lengthi1xee`:
+///
+/// `0x00000003: 6c 65 6e 67 74 68 69 31 [78] 65 65 |lengthi1[x]ee|`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Excerpt {
+    start: usize,
+    offset: usize,
+    bytes: Vec<u8>,
+}
+
+impl Excerpt {
+    pub fn new(src: &[u8], offset: usize) -> Excerpt {
+        let offset = offset.min(src.len());
+        let start = offset.saturating_sub(CONTEXT);
+        let end = (offset + CONTEXT + 1).min(src.len());
+
+        Excerpt {
+            start,
+            offset,
+            bytes: src[start..end].to_vec(),
+        }
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl Display for Excerpt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let marked = self.offset - self.start;
+
+        write!(f, "{:#010x}:", self.start)?;
+        for (i, b) in self.bytes.iter().enumerate() {
+            if i == marked {
+                write!(f, " [{b:02x}]")?;
+            } else {
+                write!(f, " {b:02x}")?;
+            }
+        }
+        if marked == self.bytes.len() {
+            write!(f, " [EOF]")?;
+        }
+
+        write!(f, " |")?;
+        for (i, b) in self.bytes.iter().enumerate() {
+            let c = if b.is_ascii_graphic() || *b == b' ' {
+                *b as char
+            } else {
+                '.'
+            };
+            if i == marked {
+                write!(f, "[{c}]")?;
+            } else {
+                write!(f, "{c}")?;
+            }
+        }
+        write!(f, "|")
+    }
+}
+
+#[cfg(test)]
+mod test_excerpt {
+    use super::*;
+
+    #[test]
+    fn marks_the_offset() {
+        let excerpt = Excerpt::new(b"d6:lengthi1xee", 11);
+
+        assert_eq!(
+            excerpt.to_string(),
+            "0x00000003: 6c 65 6e 67 74 68 69 31 [78] 65 65 |lengthi1[x]ee|"
+        );
+    }
+
+    #[test]
+    fn marks_the_end_of_input() {
+        let excerpt = Excerpt::new(b"li1\x00", 4);
+
+        assert_eq!(excerpt.to_string(), "0x00000000: 6c 69 31 00 [EOF] |li1.|");
+    }
+}
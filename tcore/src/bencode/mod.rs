@@ -1,9 +1,49 @@
+mod de;
 mod decoder;
+mod encoder;
+mod excerpt;
+mod ser;
+mod streaming;
 pub mod torrent;
+mod value;
+
+pub use de::DeserializeError;
+pub use de::Deserializer;
+pub use de::from_bytes;
+pub use de::from_prefix;
+pub use decoder::CanonicalRule;
+pub use decoder::DecodeError;
+pub use decoder::DecodeLimits;
+pub use decoder::Decoder;
+pub use decoder::DecoderOptions;
+pub use decoder::Limit;
+pub use decoder::Token;
+pub use decoder::validate_canonical;
+pub use encoder::EncodeError;
+pub use encoder::Encoder;
+pub use excerpt::Excerpt;
+pub use ser::SerializeError;
+pub use ser::Serializer;
+pub use ser::to_vec;
+pub use streaming::StreamingDecoder;
+pub use value::Value;
+pub use value::parse;
 
 pub use torrent::File;
+pub use torrent::FileSlice;
+pub use torrent::FileRef;
 pub use torrent::Info;
+pub use torrent::InfoRef;
+pub use torrent::Layout;
+pub use torrent::MappedTorrent;
+pub use torrent::PathSegment;
 pub use torrent::Torrent;
 pub use torrent::TorrentBuilderStateKind;
+pub use torrent::TorrentCreateError;
+pub use torrent::TorrentCreator;
 pub use torrent::TorrentFileError;
 pub use torrent::TorrentKey;
+pub use torrent::TorrentPath;
+pub use torrent::TorrentRef;
+pub use torrent::TreeFile;
+pub use torrent::TreeFileRef;
@@ -0,0 +1,623 @@
+#![warn(clippy::all)]
+use std::{fmt::Display, io::Write, mem, ops::Range};
+
+use serde::{
+    Serialize,
+    ser::{self, SerializeMap as _},
+};
+use thiserror::Error;
+
+use super::{
+    decoder::{Decoder, Token},
+    encoder::write_bytes,
+    value::Value,
+};
+
+#[derive(Debug, Error)]
+pub enum SerializeError {
+    #[error("error writing bencode: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("dict keys must be strings")]
+    KeyNotString,
+    #[error("duplicate dict key {0:?}")]
+    DuplicateKey(Vec<u8>),
+    #[error("{0} is not supported by bencode")]
+    Unsupported(&'static str),
+    #[error("{0}")]
+    Custom(String),
+}
+
+impl ser::Error for SerializeError {
+    fn custom<T: Display>(msg: T) -> Self {
+        SerializeError::Custom(msg.to_string())
+    }
+}
+
+/// Serializes `value` into canonical bencode: dict keys are sorted by their
+/// raw bytes and `None` values are left out. Bencode has no null, so `None`
+/// and `()` anywhere but as a dict value are an error.
+pub fn to_vec<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, SerializeError> {
+    let mut ser = Serializer::default();
+    value.serialize(&mut ser)?;
+    Ok(ser.out)
+}
+
+#[derive(Default)]
+pub struct Serializer {
+    out: Vec<u8>,
+    /// The value being serialized is a dict value, which may be `None`.
+    dict_value: bool,
+}
+
+impl Serializer {
+    pub fn into_inner(self) -> Vec<u8> {
+        self.out
+    }
+
+    fn begin_variant(&mut self, variant: &str) {
+        self.dict_value = false;
+        self.out.push(b'd');
+        write_bytes(&mut self.out, variant.as_bytes()).expect("writing to a Vec never fails");
+    }
+}
+
+impl<'a> ser::Serializer for &'a mut Serializer {
+    type Ok = ();
+    type Error = SerializeError;
+
+    type SerializeSeq = List<'a>;
+    type SerializeTuple = List<'a>;
+    type SerializeTupleStruct = List<'a>;
+    type SerializeTupleVariant = List<'a>;
+    type SerializeMap = Dict<'a>;
+    type SerializeStruct = Dict<'a>;
+    type SerializeStructVariant = Dict<'a>;
+
+    /// Byte strings hold hashes and ids as they are.
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    fn serialize_bool(self, v: bool) -> Result<(), SerializeError> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), SerializeError> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), SerializeError> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), SerializeError> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), SerializeError> {
+        write!(self.out, "i{v}e")?;
+        Ok(())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), SerializeError> {
+        self.serialize_u64(v as u64)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), SerializeError> {
+        self.serialize_u64(v as u64)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), SerializeError> {
+        self.serialize_u64(v as u64)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), SerializeError> {
+        write!(self.out, "i{v}e")?;
+        Ok(())
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<(), SerializeError> {
+        Err(SerializeError::Unsupported("f32"))
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<(), SerializeError> {
+        Err(SerializeError::Unsupported("f64"))
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), SerializeError> {
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), SerializeError> {
+        self.serialize_bytes(v.as_bytes())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), SerializeError> {
+        write_bytes(&mut self.out, v)?;
+        Ok(())
+    }
+
+    /// Produces no output at all, so the surrounding dict leaves the entry
+    /// out. Anywhere else there is nothing to write it as.
+    fn serialize_none(self) -> Result<(), SerializeError> {
+        match mem::take(&mut self.dict_value) {
+            true => Ok(()),
+            false => Err(SerializeError::Unsupported("None outside of a dict")),
+        }
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), SerializeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), SerializeError> {
+        match mem::take(&mut self.dict_value) {
+            true => Ok(()),
+            false => Err(SerializeError::Unsupported("unit outside of a dict")),
+        }
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), SerializeError> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<(), SerializeError> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        self.begin_variant(variant);
+        value.serialize(&mut *self)?;
+        self.out.push(b'e');
+        Ok(())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<List<'a>, SerializeError> {
+        self.dict_value = false;
+        self.out.push(b'l');
+        Ok(List {
+            ser: self,
+            variant: false,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<List<'a>, SerializeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<List<'a>, SerializeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<List<'a>, SerializeError> {
+        self.begin_variant(variant);
+        self.out.push(b'l');
+        Ok(List {
+            ser: self,
+            variant: true,
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Dict<'a>, SerializeError> {
+        self.dict_value = false;
+        Ok(Dict {
+            start: self.out.len(),
+            ser: self,
+            entries: Vec::new(),
+            key: None,
+            variant: false,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Dict<'a>, SerializeError> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Dict<'a>, SerializeError> {
+        self.begin_variant(variant);
+        Ok(Dict {
+            start: self.out.len(),
+            ser: self,
+            entries: Vec::new(),
+            key: None,
+            variant: true,
+        })
+    }
+}
+
+pub struct List<'a> {
+    ser: &'a mut Serializer,
+    variant: bool,
+}
+
+impl List<'_> {
+    fn element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn finish(self) -> Result<(), SerializeError> {
+        self.ser.out.push(b'e');
+        if self.variant {
+            self.ser.out.push(b'e');
+        }
+        Ok(())
+    }
+}
+
+impl ser::SerializeSeq for List<'_> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for List<'_> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for List<'_> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for List<'_> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        self.finish()
+    }
+}
+
+/// Writes the values one after another and puts them in key order at the
+/// end, since canonical bencode wants the keys sorted no matter in which
+/// order they were serialized.
+pub struct Dict<'a> {
+    ser: &'a mut Serializer,
+    /// Where the values start in the output.
+    start: usize,
+    entries: Vec<(Vec<u8>, Range<usize>)>,
+    key: Option<Vec<u8>>,
+    variant: bool,
+}
+
+impl Dict<'_> {
+    fn entry_value<T: Serialize + ?Sized>(
+        &mut self,
+        key: Vec<u8>,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        let from = self.ser.out.len();
+        self.ser.dict_value = true;
+        value.serialize(&mut *self.ser)?;
+        self.ser.dict_value = false;
+
+        // nothing written for `None`
+        let to = self.ser.out.len();
+        if to > from {
+            self.entries.push((key, from..to));
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<(), SerializeError> {
+        self.entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        if let Some(w) = self.entries.windows(2).find(|w| w[0].0 == w[1].0) {
+            return Err(SerializeError::DuplicateKey(w[0].0.clone()));
+        }
+
+        let values = self.ser.out.split_off(self.start);
+        let out = &mut self.ser.out;
+        out.push(b'd');
+        for (key, range) in &self.entries {
+            write_bytes(out, key)?;
+            out.extend_from_slice(&values[range.start - self.start..range.end - self.start]);
+        }
+        out.push(b'e');
+        if self.variant {
+            out.push(b'e');
+        }
+        Ok(())
+    }
+}
+
+/// Serializes a key and makes sure it came out as a byte string.
+fn key_bytes<T: Serialize + ?Sized>(key: &T) -> Result<Vec<u8>, SerializeError> {
+    let encoded = to_vec(key)?;
+    let mut dec = Decoder::new(&encoded);
+    match dec.next_token() {
+        Ok(Token::String(key)) if dec.is_finished() => Ok(key.into_owned()),
+        _ => Err(SerializeError::KeyNotString),
+    }
+}
+
+impl ser::SerializeMap for Dict<'_> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), SerializeError> {
+        self.key = Some(key_bytes(key)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| SerializeError::Custom("value without a key".to_string()))?;
+        self.entry_value(key, value)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for Dict<'_> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        self.entry_value(key.as_bytes().to_vec(), value)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for Dict<'_> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        self.entry_value(key.as_bytes().to_vec(), value)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        self.finish()
+    }
+}
+
+impl Serialize for Value<'_> {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Value::Int(i) => serializer.serialize_i64(*i),
+            Value::Bytes(b) => serializer.serialize_bytes(b),
+            Value::List(list) => serializer.collect_seq(list),
+            Value::Dict(dict) => {
+                let mut map = serializer.serialize_map(Some(dict.len()))?;
+                for (k, v) in dict {
+                    map.serialize_entry(&RawBytes(k), v)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+struct RawBytes<'a>(&'a [u8]);
+
+impl Serialize for RawBytes<'_> {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
+#[cfg(test)]
+mod test_ser {
+    use std::collections::BTreeMap;
+
+    use serde::Serialize;
+
+    use super::*;
+    use crate::bencode::{de::from_bytes, value::parse};
+
+    #[test]
+    fn scalars() {
+        assert_eq!(to_vec(&42u32).unwrap(), b"i42e");
+        assert_eq!(to_vec(&-1i8).unwrap(), b"i-1e");
+        assert_eq!(to_vec(&true).unwrap(), b"i1e");
+        assert_eq!(to_vec("spam").unwrap(), b"4:spam");
+        assert_eq!(
+            to_vec(serde_bytes::Bytes::new(b"\x00\xff")).unwrap(),
+            b"2:\x00\xff"
+        );
+    }
+
+    #[test]
+    fn struct_keys_are_sorted() {
+        #[derive(Serialize)]
+        struct Announce<'a> {
+            peers: Vec<&'a str>,
+            interval: u32,
+            #[serde(rename = "failure reason")]
+            failure_reason: Option<&'a str>,
+            complete: u32,
+        }
+
+        let out = to_vec(&Announce {
+            peers: vec!["a", "b"],
+            interval: 1800,
+            failure_reason: None,
+            complete: 3,
+        })
+        .unwrap();
+
+        assert_eq!(out, b"d8:completei3e8:intervali1800e5:peersl1:a1:bee");
+    }
+
+    #[test]
+    fn keys_are_sorted_by_raw_bytes() {
+        let mut map = BTreeMap::new();
+        map.insert("b", 1);
+        map.insert("B", 2);
+        map.insert("a", 3);
+
+        assert_eq!(to_vec(&map).unwrap(), b"d1:Bi2e1:ai3e1:bi1ee");
+    }
+
+    #[test]
+    fn enums() {
+        #[derive(Serialize)]
+        enum Kind {
+            #[serde(rename = "q")]
+            Query,
+            #[serde(rename = "r")]
+            Response { id: u8 },
+        }
+
+        assert_eq!(to_vec(&Kind::Query).unwrap(), b"1:q");
+        assert_eq!(
+            to_vec(&Kind::Response { id: 1 }).unwrap(),
+            b"d1:rd2:idi1eee"
+        );
+    }
+
+    #[test]
+    fn value_roundtrip() {
+        let src = b"d3:bard1:xi-1ee3:fooli1e3:abcee";
+        let value = parse(src).unwrap();
+
+        assert_eq!(to_vec(&value).unwrap(), src);
+        assert_eq!(from_bytes::<Value>(src).unwrap(), value);
+    }
+
+    #[test]
+    fn error_on_non_string_key() {
+        let mut map = BTreeMap::new();
+        map.insert(1, 1);
+
+        assert!(matches!(
+            to_vec(&map).unwrap_err(),
+            SerializeError::KeyNotString
+        ));
+    }
+
+    #[test]
+    fn error_on_none_outside_of_a_dict() {
+        #[derive(Serialize)]
+        enum Kind {
+            Variant(Option<u8>),
+        }
+
+        for out in [
+            to_vec(&None::<u8>),
+            to_vec(&()),
+            to_vec(&Kind::Variant(None)),
+            to_vec(&[Some(1), None]),
+        ] {
+            assert!(matches!(out, Err(SerializeError::Unsupported(_))));
+        }
+        assert_eq!(
+            to_vec(&BTreeMap::from([("a", None), ("b", Some(1))])).unwrap(),
+            b"d1:bi1ee"
+        );
+    }
+
+    #[test]
+    fn nested_dicts() {
+        #[derive(Serialize)]
+        struct Inner {
+            z: u8,
+            a: Option<u8>,
+        }
+        #[derive(Serialize)]
+        struct Outer {
+            y: Inner,
+            x: Vec<Inner>,
+        }
+
+        let out = to_vec(&Outer {
+            y: Inner { z: 1, a: None },
+            x: vec![Inner { z: 2, a: Some(3) }],
+        })
+        .unwrap();
+
+        assert_eq!(out, b"d1:xld1:ai3e1:zi2eee1:yd1:zi1eee");
+    }
+
+    #[test]
+    fn error_on_floats() {
+        assert!(matches!(
+            to_vec(&1.5f64).unwrap_err(),
+            SerializeError::Unsupported("f64")
+        ));
+    }
+}
@@ -0,0 +1,318 @@
+#![warn(clippy::all)]
+use std::borrow::Cow;
+
+use atoi::FromRadix10SignedChecked;
+
+use super::decoder::{DecodeError, Token};
+
+/// Longest accepted run of digits in an int or a string length,
+/// same as in [`super::decoder::Decoder`].
+const MAX_DIGITS: usize = 21;
+
+/// Push-style decoder for bencode coming in chunks, e.g. from a socket.
+///
+/// [`StreamingDecoder::next_token`] returns `Ok(None)` when the buffered
+/// bytes end in the middle of a token. Whatever was already scanned is
+/// remembered, so after the next [`StreamingDecoder::feed`] decoding
+/// resumes where it stopped. Token positions are offsets in the whole stream.
+#[derive(Default)]
+pub struct StreamingDecoder {
+    buf: Vec<u8>,
+    pos: usize,
+    offset: usize,
+    depth: usize,
+    pending: Pending,
+}
+
+#[derive(Default)]
+enum Pending {
+    #[default]
+    Nothing,
+    /// `scanned` bytes after the 'i' are known to be valid digits.
+    Int { scanned: usize },
+    /// `scanned` bytes of the length are known to be digits.
+    StringLength { scanned: usize },
+    /// The header of `header` bytes is parsed, waiting for `len` more bytes.
+    String { header: usize, len: usize },
+}
+
+impl StreamingDecoder {
+    pub fn new() -> StreamingDecoder {
+        StreamingDecoder::default()
+    }
+
+    /// Appends a chunk of input. Bytes of already returned tokens are dropped.
+    pub fn feed(&mut self, chunk: &[u8]) {
+        if self.pos > 0 {
+            self.buf.drain(..self.pos);
+            self.offset += self.pos;
+            self.pos = 0;
+        }
+        self.buf.extend_from_slice(chunk);
+    }
+
+    /// Stream offset of the next token.
+    pub fn offset(&self) -> usize {
+        self.offset + self.pos
+    }
+
+    /// Number of lists and dicts that are open at the moment.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Returns the next token, or `None` if more bytes are needed.
+    pub fn next_token(&mut self) -> Result<Option<Token<'_>>, DecodeError> {
+        if self.pos >= self.buf.len() {
+            return Ok(None);
+        }
+
+        let at = self.offset();
+        match self.buf[self.pos] {
+            b'l' => {
+                self.pos += 1;
+                self.depth += 1;
+                Ok(Some(Token::BeginList(at)))
+            }
+            b'd' => {
+                self.pos += 1;
+                self.depth += 1;
+                Ok(Some(Token::BeginDict(at)))
+            }
+            b'e' => {
+                if self.depth == 0 {
+                    return Err(DecodeError::UnexpectedObjectClosure(at));
+                }
+                self.pos += 1;
+                self.depth -= 1;
+                Ok(Some(Token::EndObject(at)))
+            }
+            b'i' => self.next_int(),
+            b'0'..=b'9' => self.next_string(),
+            v => Err(DecodeError::UnknownToken(v)),
+        }
+    }
+
+    /// Checks that the input did not stop in the middle of a value.
+    pub fn finish(&self) -> Result<(), DecodeError> {
+        match self.pending {
+            Pending::Int { .. } => Err(DecodeError::UnfinishedInt),
+            Pending::StringLength { .. } => Err(DecodeError::MissingColonInString),
+            Pending::String { len, header } => Err(DecodeError::UnfinishedString(
+                self.offset(),
+                len,
+                self.buf.len() - self.pos - header,
+            )),
+            Pending::Nothing if self.depth > 0 || self.pos < self.buf.len() => {
+                Err(DecodeError::PosOutOfBounds)
+            }
+            Pending::Nothing => Ok(()),
+        }
+    }
+
+    fn next_int(&mut self) -> Result<Option<Token<'_>>, DecodeError> {
+        let digits = self.pos + 1;
+        let mut scanned = match self.pending {
+            Pending::Int { scanned } => scanned,
+            _ => 0,
+        };
+
+        while let Some(&b) = self.buf.get(digits + scanned) {
+            match b {
+                b'e' => {
+                    let (maybe_n, used) =
+                        i64::from_radix_10_signed_checked(&self.buf[digits..digits + scanned]);
+                    return match maybe_n {
+                        Some(n) if used == scanned => {
+                            self.pos = digits + scanned + 1;
+                            self.pending = Pending::Nothing;
+                            Ok(Some(Token::Int(n)))
+                        }
+                        _ => Err(DecodeError::WrongSyntax),
+                    };
+                }
+                b'0'..=b'9' | b'-' => scanned += 1,
+                _ => return Err(DecodeError::WrongSyntax),
+            }
+
+            if scanned > MAX_DIGITS {
+                return Err(DecodeError::TokenTooLarge);
+            }
+        }
+
+        self.pending = Pending::Int { scanned };
+        Ok(None)
+    }
+
+    fn next_string(&mut self) -> Result<Option<Token<'_>>, DecodeError> {
+        let (header, len) = match self.pending {
+            Pending::String { header, len } => (header, len),
+            Pending::StringLength { scanned } => match self.scan_length(scanned)? {
+                Some(parsed) => parsed,
+                None => return Ok(None),
+            },
+            _ => match self.scan_length(0)? {
+                Some(parsed) => parsed,
+                None => return Ok(None),
+            },
+        };
+
+        let begin = self.pos + header;
+        if self.buf.len() - begin < len {
+            self.pending = Pending::String { header, len };
+            return Ok(None);
+        }
+
+        self.pos = begin + len;
+        self.pending = Pending::Nothing;
+        Ok(Some(Token::String(Cow::Borrowed(
+            &self.buf[begin..begin + len],
+        ))))
+    }
+
+    fn scan_length(&mut self, mut scanned: usize) -> Result<Option<(usize, usize)>, DecodeError> {
+        while let Some(&b) = self.buf.get(self.pos + scanned) {
+            match b {
+                b':' => {
+                    let (maybe_len, used) =
+                        u64::from_radix_10_signed_checked(&self.buf[self.pos..self.pos + scanned]);
+                    return match maybe_len {
+                        Some(len) if used == scanned => Ok(Some((scanned + 1, len as usize))),
+                        _ => Err(DecodeError::WrongSyntax),
+                    };
+                }
+                b'0'..=b'9' => scanned += 1,
+                _ => return Err(DecodeError::WrongSyntax),
+            }
+
+            if scanned > MAX_DIGITS {
+                return Err(DecodeError::TokenTooLarge);
+            }
+        }
+
+        self.pending = Pending::StringLength { scanned };
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod test_streaming {
+    use super::*;
+
+    fn collect(dec: &mut StreamingDecoder, out: &mut Vec<String>) {
+        while let Some(token) = dec.next_token().unwrap() {
+            out.push(format!("{token:?}"));
+        }
+    }
+
+    #[test]
+    fn byte_by_byte_matches_whole_buffer() {
+        let input = b"d3:fooli42ei-7ee4:spam10:0123456789e";
+
+        let mut whole = StreamingDecoder::new();
+        let mut expected = Vec::new();
+        whole.feed(input);
+        collect(&mut whole, &mut expected);
+
+        let mut dec = StreamingDecoder::new();
+        let mut got = Vec::new();
+        for b in input {
+            dec.feed(&[*b]);
+            collect(&mut dec, &mut got);
+        }
+
+        assert_eq!(got, expected);
+        assert_eq!(got.len(), 9);
+        assert_eq!(dec.depth(), 0);
+        dec.finish().unwrap();
+    }
+
+    #[test]
+    fn positions_are_stream_offsets() {
+        let mut dec = StreamingDecoder::new();
+        dec.feed(b"l4:sp");
+        assert_eq!(dec.next_token().unwrap(), Some(Token::BeginList(0)));
+        assert_eq!(dec.next_token().unwrap(), None);
+
+        dec.feed(b"ame");
+        assert_eq!(
+            dec.next_token().unwrap(),
+            Some(Token::String(Cow::Borrowed(b"spam")))
+        );
+        assert_eq!(dec.offset(), 7);
+        assert_eq!(dec.next_token().unwrap(), Some(Token::EndObject(7)));
+    }
+
+    #[test]
+    fn need_more_is_not_an_error() {
+        let mut dec = StreamingDecoder::new();
+        dec.feed(b"i12");
+        assert_eq!(dec.next_token().unwrap(), None);
+        assert!(matches!(
+            dec.finish().unwrap_err(),
+            DecodeError::UnfinishedInt
+        ));
+
+        dec.feed(b"3e");
+        assert_eq!(dec.next_token().unwrap(), Some(Token::Int(123)));
+        dec.finish().unwrap();
+    }
+
+    #[test]
+    fn error_on_unfinished_string() {
+        let mut dec = StreamingDecoder::new();
+        dec.feed(b"5:ab");
+        assert_eq!(dec.next_token().unwrap(), None);
+
+        assert!(matches!(
+            dec.finish().unwrap_err(),
+            DecodeError::UnfinishedString(0, 5, 2)
+        ));
+    }
+
+    #[test]
+    fn syntax_errors_are_reported_early() {
+        let mut dec = StreamingDecoder::new();
+        dec.feed(b"i4x");
+        assert!(matches!(
+            dec.next_token().unwrap_err(),
+            DecodeError::WrongSyntax
+        ));
+
+        let mut dec = StreamingDecoder::new();
+        dec.feed(b"3x");
+        assert!(matches!(
+            dec.next_token().unwrap_err(),
+            DecodeError::WrongSyntax
+        ));
+
+        let mut dec = StreamingDecoder::new();
+        dec.feed(b"x");
+        assert!(matches!(
+            dec.next_token().unwrap_err(),
+            DecodeError::UnknownToken(b'x')
+        ));
+    }
+
+    #[test]
+    fn error_on_too_long_length() {
+        let mut dec = StreamingDecoder::new();
+        dec.feed(&b"1".repeat(MAX_DIGITS + 1));
+
+        assert!(matches!(
+            dec.next_token().unwrap_err(),
+            DecodeError::TokenTooLarge
+        ));
+    }
+
+    #[test]
+    fn error_on_unbalanced_closure() {
+        let mut dec = StreamingDecoder::new();
+        dec.feed(b"e");
+
+        assert!(matches!(
+            dec.next_token().unwrap_err(),
+            DecodeError::UnexpectedObjectClosure(0)
+        ));
+    }
+}
@@ -0,0 +1,450 @@
+#![warn(clippy::all)]
+use std::{
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, SubsecRound, Utc};
+use thiserror::Error;
+
+use crate::{bencode::EncodeError, cryptos::hash::make_sha1};
+
+use super::{File, Info, Torrent};
+
+const MIN_PIECE_LENGTH: u64 = 16 * 1024;
+const MAX_PIECE_LENGTH: u64 = 16 * 1024 * 1024;
+
+/// Picked piece length keeps the number of pieces around this or below.
+const TARGET_PIECES: u64 = 2000;
+
+/// Makes a torrent out of a file or a directory.
+///
+/// Files of a directory are walked recursively in name order and hashed as
+/// one stream, so pieces span file boundaries as BEP 3 requires.
+pub struct TorrentCreator {
+    root: PathBuf,
+    piece_length: Option<u64>,
+    tiers: Vec<Vec<String>>,
+    comment: Option<String>,
+    creation_date: Option<DateTime<Utc>>,
+    private: bool,
+}
+
+#[derive(Error, Debug)]
+pub enum TorrentCreateError {
+    #[error("i/o error: {0}")]
+    Io(#[from] io::Error),
+    #[error("error while encoding .torrent file: {0}")]
+    Encode(#[from] EncodeError),
+    #[error("{} has no content to share", .0.display())]
+    Empty(PathBuf),
+    #[error("{} has no valid UTF-8 name", .0.display())]
+    InvalidName(PathBuf),
+    #[error("{} changed while it was hashed", .0.display())]
+    FileChanged(PathBuf),
+    #[error("piece length {0} is not a power of two of at least 16 KiB")]
+    InvalidPieceLength(u64),
+}
+
+struct Entry {
+    path: PathBuf,
+    parts: Vec<String>,
+    length: u64,
+    executable: bool,
+}
+
+impl TorrentCreator {
+    pub fn new(root: impl Into<PathBuf>) -> TorrentCreator {
+        TorrentCreator {
+            root: root.into(),
+            piece_length: None,
+            tiers: Vec::new(),
+            comment: None,
+            creation_date: None,
+            private: false,
+        }
+    }
+
+    /// Picked from the total size when not set.
+    pub fn piece_length(mut self, piece_length: u64) -> Self {
+        self.piece_length = Some(piece_length);
+        self
+    }
+
+    /// Adds a tier of trackers. The first tracker of the first tier
+    /// also goes to `announce`.
+    pub fn tier(mut self, urls: Vec<String>) -> Self {
+        if !urls.is_empty() {
+            self.tiers.push(urls);
+        }
+        self
+    }
+
+    pub fn comment(mut self, comment: impl Into<String>) -> Self {
+        self.comment = Some(comment.into());
+        self
+    }
+
+    /// Defaults to the moment the torrent is created.
+    pub fn creation_date(mut self, date: DateTime<Utc>) -> Self {
+        self.creation_date = Some(date);
+        self
+    }
+
+    pub fn private(mut self, private: bool) -> Self {
+        self.private = private;
+        self
+    }
+
+    /// Hashes the content and builds the torrent. `progress` gets the
+    /// number of bytes hashed so far and the total number of bytes.
+    pub fn create(
+        &self,
+        mut progress: impl FnMut(u64, u64),
+    ) -> Result<Torrent, TorrentCreateError> {
+        let root = fs::canonicalize(&self.root)?;
+        let name = file_name(&root)?;
+        let is_dir = fs::metadata(&root)?.is_dir();
+
+        let entries = if is_dir {
+            let mut entries = Vec::new();
+            walk(&root, &mut Vec::new(), &mut entries)?;
+            entries
+        } else {
+            vec![Entry {
+                length: fs::metadata(&root)?.len(),
+                path: root.clone(),
+                parts: Vec::new(),
+                // single files have no `attr` to put it in
+                executable: false,
+            }]
+        };
+
+        let total: u64 = entries.iter().map(|e| e.length).sum();
+        if total == 0 {
+            return Err(TorrentCreateError::Empty(root));
+        }
+
+        let piece_length = match self.piece_length {
+            Some(len) if len < MIN_PIECE_LENGTH || !len.is_power_of_two() => {
+                return Err(TorrentCreateError::InvalidPieceLength(len));
+            }
+            Some(len) => len,
+            None => auto_piece_length(total),
+        };
+
+        let mut info = Info {
+            name_raw: name.clone().into_bytes(),
+            name,
+            piece_length,
+            pieces: hash_pieces(&entries, piece_length, total, &mut progress)?,
+            private: self.private,
+            ..Default::default()
+        };
+        if is_dir {
+            let files = entries
+                .into_iter()
+                .map(|e| File {
+                    length: e.length as usize,
+                    path_raw: e
+                        .parts
+                        .iter()
+                        .map(|part| part.clone().into_bytes())
+                        .collect(),
+                    path: e.parts,
+                    attr: e.executable.then(|| "x".to_string()),
+                    ..Default::default()
+                })
+                .collect();
+            info.files = Some(files);
+        } else {
+            info.length = Some(total);
+        }
+        info.raw = info.to_bytes()?;
+        info.info_hash = make_sha1(&info.raw);
+
+        let trackers: usize = self.tiers.iter().map(Vec::len).sum();
+        Ok(Torrent {
+            announce: self.tiers.first().map(|t| t[0].clone()).unwrap_or_default(),
+            announce_list: if trackers > 1 {
+                self.tiers.clone()
+            } else {
+                Vec::new()
+            },
+            comment: self.comment.clone(),
+            created_by: Some(concat!("tcore/", env!("CARGO_PKG_VERSION")).to_string()),
+            // the file only keeps whole seconds
+            creation_date: Some(self.creation_date.unwrap_or_else(Utc::now).trunc_subsecs(0)),
+            info,
+            ..Default::default()
+        })
+    }
+
+    /// Same as [`TorrentCreator::create`], then writes the .torrent file to `out`.
+    pub fn create_file(
+        &self,
+        out: impl AsRef<Path>,
+        progress: impl FnMut(u64, u64),
+    ) -> Result<Torrent, TorrentCreateError> {
+        let torrent = self.create(progress)?;
+        fs::write(out, torrent.to_bytes()?)?;
+        Ok(torrent)
+    }
+}
+
+fn auto_piece_length(total: u64) -> u64 {
+    let mut len = MIN_PIECE_LENGTH;
+    while len < MAX_PIECE_LENGTH && total.div_ceil(len) > TARGET_PIECES {
+        len *= 2;
+    }
+    len
+}
+
+fn file_name(path: &Path) -> Result<String, TorrentCreateError> {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .map(str::to_owned)
+        .ok_or_else(|| TorrentCreateError::InvalidName(path.to_path_buf()))
+}
+
+#[cfg(unix)]
+fn is_executable(meta: &fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    meta.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_: &fs::Metadata) -> bool {
+    false
+}
+
+/// Collects the regular files under `dir`, in name order. Symlinks are
+/// not followed, they could point anywhere outside of `dir`, and are left
+/// out along with sockets, fifos and devices.
+fn walk(
+    dir: &Path,
+    parts: &mut Vec<String>,
+    out: &mut Vec<Entry>,
+) -> Result<(), TorrentCreateError> {
+    let mut children = fs::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+    children.sort_by_key(|child| child.file_name());
+
+    for child in children {
+        let path = child.path();
+        parts.push(file_name(&path)?);
+
+        let meta = fs::symlink_metadata(&path)?;
+        if meta.is_dir() {
+            walk(&path, parts, out)?;
+        } else if meta.is_file() {
+            out.push(Entry {
+                path,
+                parts: parts.clone(),
+                length: meta.len(),
+                executable: is_executable(&meta),
+            });
+        }
+        parts.pop();
+    }
+    Ok(())
+}
+
+fn hash_pieces(
+    entries: &[Entry],
+    piece_length: u64,
+    total: u64,
+    progress: &mut impl FnMut(u64, u64),
+) -> Result<Vec<u8>, TorrentCreateError> {
+    let mut pieces = Vec::new();
+    let mut piece = Vec::with_capacity(piece_length as usize);
+    let mut hashed = 0;
+
+    for entry in entries {
+        let mut file = fs::File::open(&entry.path)?;
+        let mut read = 0;
+
+        loop {
+            let want = piece_length - piece.len() as u64;
+            let n = (&mut file).take(want).read_to_end(&mut piece)?;
+            if n == 0 {
+                break;
+            }
+            read += n as u64;
+
+            if piece.len() as u64 == piece_length {
+                pieces.extend_from_slice(&make_sha1(&piece));
+                hashed += piece_length;
+                piece.clear();
+                progress(hashed, total);
+            }
+        }
+
+        if read != entry.length {
+            return Err(TorrentCreateError::FileChanged(entry.path.clone()));
+        }
+    }
+
+    // the last piece is allowed to be shorter:
+    if !piece.is_empty() {
+        pieces.extend_from_slice(&make_sha1(&piece));
+        hashed += piece.len() as u64;
+        progress(hashed, total);
+    }
+    Ok(pieces)
+}
+
+#[cfg(test)]
+mod test_creator {
+    use super::*;
+
+    const PIECE: u64 = MIN_PIECE_LENGTH;
+
+    fn content(len: usize, seed: u8) -> Vec<u8> {
+        (0..len)
+            .map(|i| (i as u8).wrapping_mul(31).wrapping_add(seed))
+            .collect()
+    }
+
+    #[test]
+    fn single_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data.bin");
+        let data = content(PIECE as usize + 100, 1);
+        fs::write(&path, &data).unwrap();
+
+        let torrent = TorrentCreator::new(&path)
+            .piece_length(PIECE)
+            .tier(vec!["http://tracker".into()])
+            .create(|_, _| {})
+            .unwrap();
+
+        assert_eq!(torrent.info.name, "data.bin");
+        assert_eq!(torrent.info.length, Some(data.len() as u64));
+        assert!(torrent.info.files.is_none());
+        assert_eq!(torrent.announce, "http://tracker");
+        assert!(torrent.announce_list.is_empty());
+
+        let expected = [
+            make_sha1(&data[..PIECE as usize]),
+            make_sha1(&data[PIECE as usize..]),
+        ];
+        assert_eq!(torrent.info.pieces, expected.concat());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn executable_files_are_marked() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("bundle");
+        fs::create_dir(&root).unwrap();
+        fs::write(root.join("install.sh"), b"#!/bin/sh").unwrap();
+        fs::write(root.join("readme"), b"hi").unwrap();
+        fs::set_permissions(root.join("install.sh"), fs::Permissions::from_mode(0o755)).unwrap();
+
+        let torrent = TorrentCreator::new(&root).create(|_, _| {}).unwrap();
+        let files = torrent.info.files.unwrap();
+
+        assert!(files[0].is_executable());
+        assert!(!files[1].is_executable());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("content");
+        fs::create_dir(&root).unwrap();
+        fs::write(dir.path().join("secret"), b"outside").unwrap();
+        fs::write(root.join("a"), b"inside").unwrap();
+        std::os::unix::fs::symlink(dir.path().join("secret"), root.join("link")).unwrap();
+        std::os::unix::fs::symlink(dir.path(), root.join("up")).unwrap();
+
+        let torrent = TorrentCreator::new(&root).create(|_, _| {}).unwrap();
+        let files = torrent.info.files.unwrap();
+
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, ["a"]);
+        assert_eq!(torrent.info.pieces, make_sha1(b"inside"));
+    }
+
+    #[test]
+    fn pieces_span_file_boundaries() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("content");
+        fs::create_dir_all(root.join("sub")).unwrap();
+
+        let a = content(PIECE as usize / 2, 1);
+        let b = content(PIECE as usize, 2);
+        fs::write(root.join("sub").join("b"), &b).unwrap();
+        fs::write(root.join("a"), &a).unwrap();
+
+        let torrent = TorrentCreator::new(&root)
+            .piece_length(PIECE)
+            .create(|_, _| {})
+            .unwrap();
+
+        let files = torrent.info.files.as_ref().unwrap();
+        assert_eq!(files[0].path, ["a"]);
+        assert_eq!(files[1].path, ["sub", "b"]);
+
+        let stream = [a, b].concat();
+        let expected = [
+            make_sha1(&stream[..PIECE as usize]),
+            make_sha1(&stream[PIECE as usize..]),
+        ];
+        assert_eq!(torrent.info.pieces, expected.concat());
+    }
+
+    #[test]
+    fn written_file_parses_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data.bin");
+        fs::write(&path, content(3 * PIECE as usize, 7)).unwrap();
+        let out = dir.path().join("data.torrent");
+
+        let mut calls = Vec::new();
+        let created = TorrentCreator::new(&path)
+            .tier(vec!["http://a".into(), "http://b".into()])
+            .tier(vec!["http://c".into()])
+            .comment("test")
+            .private(true)
+            .create_file(&out, |hashed, total| calls.push((hashed, total)))
+            .unwrap();
+
+        assert_eq!(calls.last(), Some(&(3 * PIECE, 3 * PIECE)));
+        assert_eq!(calls.len(), 3);
+
+        let parsed = Torrent::from_file(out.to_str().unwrap()).unwrap();
+        assert_eq!(parsed.info_hash(), Some(created.info.info_hash.into()));
+        assert_eq!(parsed.info.raw, created.info.raw);
+        assert_eq!(parsed.announce_list, created.announce_list);
+        assert_eq!(parsed.announce, "http://a");
+        assert!(parsed.info.private);
+        assert_eq!(parsed.comment.as_deref(), Some("test"));
+        assert_eq!(parsed.created_by, created.created_by);
+        assert_eq!(parsed.creation_date, created.creation_date);
+    }
+
+    #[test]
+    fn error_on_invalid_piece_length() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data.bin");
+        fs::write(&path, b"data").unwrap();
+
+        let err = TorrentCreator::new(&path)
+            .piece_length(PIECE + 1)
+            .create(|_, _| {})
+            .unwrap_err();
+        assert!(matches!(err, TorrentCreateError::InvalidPieceLength(_)));
+    }
+
+    #[test]
+    fn auto_piece_length_is_bounded() {
+        assert_eq!(auto_piece_length(1), MIN_PIECE_LENGTH);
+        assert_eq!(auto_piece_length(TARGET_PIECES * 64 * 1024), 64 * 1024);
+        assert_eq!(auto_piece_length(u64::MAX / 2), MAX_PIECE_LENGTH);
+    }
+}
@@ -0,0 +1,234 @@
+use std::{borrow::Cow, ops::Range};
+
+use super::{Info, TorrentFileError};
+
+/// Where the pieces of a v1 torrent fall: its files laid one after another,
+/// padding included, and cut into pieces of `piece_length` bytes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Layout<'a> {
+    piece_length: u64,
+    /// Where each file starts, followed by the total length.
+    offsets: Vec<u64>,
+    pieces: Cow<'a, [u8]>,
+}
+
+/// The part of one file that a piece covers.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FileSlice {
+    /// Index into the files of the torrent.
+    pub file: usize,
+    /// Where the slice starts within the file.
+    pub offset: u64,
+    pub length: u64,
+}
+
+impl<'a> Layout<'a> {
+    /// A single-file torrent is laid out as one file. Parsing rejects
+    /// lengths that overflow, those of an `Info` made by hand stop at
+    /// `u64::MAX`.
+    pub fn new(info: &'a Info) -> Layout<'a> {
+        let lengths: Vec<u64> = match &info.files {
+            Some(files) => files.iter().map(|file| file.length()).collect(),
+            None => vec![info.total_length()],
+        };
+        let mut offsets = vec![0u64];
+        for length in lengths {
+            offsets.push(offsets[offsets.len() - 1].saturating_add(length));
+        }
+        Layout {
+            piece_length: info.piece_length,
+            offsets,
+            pieces: Cow::Borrowed(&info.pieces),
+        }
+    }
+
+    pub(super) fn from_parts(
+        piece_length: u64,
+        lengths: impl IntoIterator<Item = u64>,
+        pieces: Cow<'a, [u8]>,
+    ) -> Result<Layout<'a>, TorrentFileError> {
+        let mut offsets = vec![0u64];
+        for length in lengths {
+            let end = offsets[offsets.len() - 1]
+                .checked_add(length)
+                .ok_or(TorrentFileError::LengthOverflow)?;
+            offsets.push(end);
+        }
+        Ok(Layout {
+            piece_length,
+            offsets,
+            pieces,
+        })
+    }
+
+    pub fn into_owned(self) -> Layout<'static> {
+        Layout {
+            piece_length: self.piece_length,
+            offsets: self.offsets,
+            pieces: Cow::Owned(self.pieces.into_owned()),
+        }
+    }
+
+    pub fn piece_length(&self) -> u64 {
+        self.piece_length
+    }
+
+    pub fn total_length(&self) -> u64 {
+        self.offsets[self.offsets.len() - 1]
+    }
+
+    pub fn file_count(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    /// Pieces needed for the total length, whatever `pieces` holds.
+    pub fn piece_count(&self) -> usize {
+        match self.piece_length {
+            0 => 0,
+            piece_length => self.total_length().div_ceil(piece_length) as usize,
+        }
+    }
+
+    /// Only the last piece may be shorter than the piece length.
+    pub fn piece_len(&self, piece: usize) -> u64 {
+        let range = self.piece_range(piece);
+        range.end - range.start
+    }
+
+    pub fn last_piece_len(&self) -> u64 {
+        match self.piece_count() {
+            0 => 0,
+            count => self.piece_len(count - 1),
+        }
+    }
+
+    /// Bytes of the whole torrent the piece covers, empty past the end.
+    pub fn piece_range(&self, piece: usize) -> Range<u64> {
+        let total = self.total_length();
+        let start = (piece as u64).saturating_mul(self.piece_length).min(total);
+        start..start.saturating_add(self.piece_length).min(total)
+    }
+
+    /// Parts of the files a piece covers, in order. Empty files are skipped.
+    pub fn file_slices(&self, piece: usize) -> Vec<FileSlice> {
+        let Range { start, end } = self.piece_range(piece);
+        // the first file ending after the start of the piece
+        let first = self.offsets[1..].partition_point(|&file_end| file_end <= start);
+
+        (first..self.file_count())
+            .map(|file| (file, self.offsets[file], self.offsets[file + 1]))
+            .take_while(|&(_, file_start, _)| file_start < end)
+            .filter(|&(_, file_start, file_end)| file_end > file_start)
+            .map(|(file, file_start, file_end)| {
+                let from = start.max(file_start);
+                FileSlice {
+                    file,
+                    offset: from - file_start,
+                    length: end.min(file_end) - from,
+                }
+            })
+            .collect()
+    }
+
+    /// Pieces holding a part of the file, none for an empty file.
+    pub fn file_pieces(&self, file: usize) -> Range<usize> {
+        let (start, end) = (self.offsets[file], self.offsets[file + 1]);
+        if start == end || self.piece_length == 0 {
+            return 0..0;
+        }
+        (start / self.piece_length) as usize..end.div_ceil(self.piece_length) as usize
+    }
+
+    /// SHA-1 of a piece, if `pieces` has one for it.
+    pub fn piece_hash(&self, piece: usize) -> Option<[u8; 20]> {
+        let start = piece.checked_mul(20)?;
+        let hash = self.pieces.get(start..start.checked_add(20)?)?;
+        hash.try_into().ok()
+    }
+
+    /// SHA-1 of every piece in order. A trailing partial hash is left out.
+    pub fn hashes(&self) -> impl Iterator<Item = [u8; 20]> + '_ {
+        self.pieces
+            .chunks_exact(20)
+            .map(|hash| hash.try_into().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod test_layout {
+    use super::*;
+
+    /// Files of 3, 0 and 7 bytes in pieces of 4.
+    fn layout() -> Layout<'static> {
+        let pieces = [[1; 20], [2; 20], [3; 20]].concat();
+        Layout::from_parts(4, [3, 0, 7], Cow::Owned(pieces)).unwrap()
+    }
+
+    #[test]
+    fn pieces_span_file_boundaries() {
+        let layout = layout();
+
+        assert_eq!(layout.total_length(), 10);
+        assert_eq!(layout.piece_count(), 3);
+        assert_eq!(layout.piece_len(0), 4);
+        assert_eq!(layout.last_piece_len(), 2);
+        assert_eq!(layout.piece_range(2), 8..10);
+        assert_eq!(
+            layout.file_slices(0),
+            [
+                FileSlice {
+                    file: 0,
+                    offset: 0,
+                    length: 3
+                },
+                FileSlice {
+                    file: 2,
+                    offset: 0,
+                    length: 1
+                },
+            ]
+        );
+        assert_eq!(
+            layout.file_slices(2),
+            [FileSlice {
+                file: 2,
+                offset: 5,
+                length: 2
+            }]
+        );
+        assert!(layout.file_slices(3).is_empty());
+    }
+
+    #[test]
+    fn files_are_covered_by_pieces() {
+        let layout = layout();
+
+        assert_eq!(layout.file_pieces(0), 0..1);
+        assert_eq!(layout.file_pieces(1), 0..0);
+        assert_eq!(layout.file_pieces(2), 0..3);
+
+        let aligned = Layout::from_parts(4, [4, 8], Cow::Borrowed(&[][..])).unwrap();
+        assert_eq!(aligned.file_pieces(0), 0..1);
+        assert_eq!(aligned.file_pieces(1), 1..3);
+        assert_eq!(aligned.last_piece_len(), 4);
+    }
+
+    #[test]
+    fn hashes_are_split() {
+        let layout = layout();
+
+        assert_eq!(
+            layout.hashes().collect::<Vec<_>>(),
+            [[1; 20], [2; 20], [3; 20]]
+        );
+        assert_eq!(layout.piece_hash(1), Some([2; 20]));
+        assert_eq!(layout.piece_hash(3), None);
+        assert_eq!(layout.piece_hash(usize::MAX), None);
+    }
+
+    #[test]
+    fn error_on_length_overflow() {
+        let res = Layout::from_parts(4, [u64::MAX, 1], Cow::Borrowed(&[][..]));
+        assert!(matches!(res, Err(TorrentFileError::LengthOverflow)));
+    }
+}
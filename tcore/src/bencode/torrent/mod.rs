@@ -0,0 +1,3163 @@
+#![warn(clippy::all)]
+mod creator;
+mod layout;
+
+use std::{borrow::Cow, collections::BTreeMap, fmt::Display, fs, io::Write, path::Path};
+
+use chrono::{DateTime, Utc};
+use encoding_rs::{Encoding, UTF_8};
+use memmap2::Mmap;
+
+use thiserror::Error;
+
+use crate::{
+    cryptos::hash::{make_sha1, make_sha256},
+    ids::InfoHash,
+};
+
+use super::{
+    decoder::{DecodeError, DecodeLimits, Decoder, DecoderOptions, Limit, Token, TokenKind},
+    encoder::{EncodeError, Encoder},
+    excerpt::Excerpt,
+};
+
+pub use creator::TorrentCreateError;
+pub use creator::TorrentCreator;
+pub use layout::FileSlice;
+pub use layout::Layout;
+
+/// Smallest piece length of v2 torrents, which must be powers of two
+/// (BEP 52).
+const MIN_V2_PIECE_LENGTH: u64 = 16 * 1024;
+
+#[derive(Default, Debug)]
+pub struct Torrent {
+    pub announce: String,
+    /// Tiers of backup trackers from `announce-list` (BEP 12).
+    pub announce_list: Vec<Vec<String>>,
+    pub comment: Option<String>,
+    pub created_by: Option<String>,
+    pub creation_date: Option<DateTime<Utc>>,
+    /// Charset the creator claims its strings are in.
+    pub encoding: Option<String>,
+    pub info: Info,
+    /// DHT nodes to bootstrap from as host and port (BEP 5).
+    pub nodes: Vec<(String, u16)>,
+    /// SHA-256 hashes of every piece, keyed by the `pieces root` of the file (BEP 52).
+    pub piece_layers: BTreeMap<[u8; 32], Vec<u8>>,
+    /// Web seeds from `url-list` (BEP 19).
+    pub url_list: Vec<String>,
+}
+
+impl Torrent {
+    pub fn total_length(&self) -> u64 {
+        self.info.total_length()
+    }
+}
+
+#[derive(Default, Debug, PartialEq)]
+pub struct Info {
+    pub info_hash: [u8; 20],
+
+    /// Readable name: `name.utf-8` if given, else `name` decoded with the
+    /// torrent's `encoding`, with undecodable bytes replaced.
+    pub name: String,
+    /// `name` exactly as stored. Empty for an `Info` built by hand.
+    pub name_raw: Vec<u8>,
+    /// Added next to a legacy `name` by some clients.
+    pub name_utf8: Option<String>,
+    pub piece_length: u64,
+    pub pieces: Vec<u8>,
+    pub length: Option<u64>,
+    pub files: Option<Vec<File>>,
+    /// Peers come from trackers only (BEP 27).
+    pub private: bool,
+
+    /// 2 for v2 and hybrid torrents (BEP 52).
+    pub meta_version: Option<i64>,
+    /// SHA-256 of the info dict, only for v2 and hybrid torrents.
+    pub info_hash_v2: Option<[u8; 32]>,
+    /// Files of the `file tree`, flattened in tree order.
+    pub file_tree: Option<Vec<TreeFile>>,
+
+    /// The info dict exactly as it was parsed, which is what peers fetching
+    /// the metadata get (BEP 9). Empty for an `Info` built by hand.
+    pub raw: Vec<u8>,
+}
+
+impl Info {
+    /// Parsing rejects lengths that add up past `u64::MAX`, those of an
+    /// `Info` made by hand stop there.
+    pub fn total_length(&self) -> u64 {
+        if let Some(length) = self.length {
+            return length;
+        }
+
+        if let Some(files) = &self.files {
+            return files
+                .iter()
+                .fold(0, |total, file| total.saturating_add(file.length()));
+        }
+
+        if let Some(tree) = &self.file_tree {
+            return tree
+                .iter()
+                .fold(0, |total, file| total.saturating_add(file.length));
+        }
+
+        0
+    }
+
+    /// Geometry of the v1 pieces over the files.
+    pub fn layout(&self) -> Layout<'_> {
+        Layout::new(self)
+    }
+
+    /// v2 pieces never span files, each file starts a new one, so v2-only
+    /// torrents count them per file. Counts that don't fit stop at
+    /// `usize::MAX`.
+    pub fn piece_count(&self) -> usize {
+        match &self.file_tree {
+            Some(tree) if !self.is_v1() && self.piece_length > 0 => tree
+                .iter()
+                .map(|file| file.length.div_ceil(self.piece_length))
+                .fold(0, |count: usize, pieces| {
+                    count.saturating_add(usize::try_from(pieces).unwrap_or(usize::MAX))
+                }),
+            _ => self.layout().piece_count(),
+        }
+    }
+
+    /// Has the v1 `pieces`, which v1 and hybrid torrents can't go without.
+    pub fn is_v1(&self) -> bool {
+        !self.pieces.is_empty()
+    }
+
+    pub fn is_v2(&self) -> bool {
+        self.meta_version == Some(2)
+    }
+
+    /// Carries both the v1 and the v2 keys, describing the same content.
+    pub fn is_hybrid(&self) -> bool {
+        self.is_v1() && self.is_v2()
+    }
+}
+
+#[derive(Default, Debug, PartialEq)]
+pub struct File {
+    length: usize,
+    /// Readable components, decoded like [`Info::name`].
+    path: Vec<String>,
+    path_raw: Vec<Vec<u8>>,
+    path_utf8: Option<Vec<String>>,
+    /// BEP 47 attributes: "p" padding, "x" executable, "h" hidden, "l" symlink.
+    attr: Option<String>,
+    /// Target of a symlink, relative to the torrent's directory.
+    symlink_path: Option<Vec<String>>,
+    sha1: Option<[u8; 20]>,
+}
+
+impl File {
+    pub fn length(&self) -> u64 {
+        self.length as u64
+    }
+
+    /// Path components below the torrent's directory.
+    pub fn path(&self) -> &[String] {
+        &self.path
+    }
+
+    /// Path components exactly as stored.
+    pub fn path_raw(&self) -> &[Vec<u8>] {
+        &self.path_raw
+    }
+
+    pub fn is_padding(&self) -> bool {
+        self.has_attr('p')
+    }
+
+    pub fn is_executable(&self) -> bool {
+        self.has_attr('x')
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        self.has_attr('h')
+    }
+
+    pub fn is_symlink(&self) -> bool {
+        self.has_attr('l')
+    }
+
+    pub fn symlink_path(&self) -> Option<&[String]> {
+        self.symlink_path.as_deref()
+    }
+
+    /// SHA-1 of the whole file, if the creator included it.
+    pub fn sha1(&self) -> Option<[u8; 20]> {
+        self.sha1
+    }
+
+    fn has_attr(&self, attr: char) -> bool {
+        self.attr.as_ref().is_some_and(|attrs| attrs.contains(attr))
+    }
+}
+
+/// A file from the v2 `file tree`.
+#[derive(Default, Debug, PartialEq)]
+pub struct TreeFile {
+    pub path: Vec<String>,
+    pub length: u64,
+    /// Root of the file's merkle tree, none for empty files.
+    pub pieces_root: Option<[u8; 32]>,
+}
+
+/// A [`Torrent`] borrowing its strings and `pieces` from the buffer it
+/// was parsed from, e.g. a [`MappedTorrent`]. Use [`TorrentRef::into_owned`]
+/// to detach it.
+#[derive(Default, Debug)]
+pub struct TorrentRef<'a> {
+    pub announce: Cow<'a, str>,
+    pub announce_list: Vec<Vec<Cow<'a, str>>>,
+    pub comment: Option<Cow<'a, str>>,
+    pub created_by: Option<Cow<'a, str>>,
+    pub creation_date: Option<DateTime<Utc>>,
+    pub encoding: Option<Cow<'a, str>>,
+    pub info: InfoRef<'a>,
+    pub nodes: Vec<(Cow<'a, str>, u16)>,
+    pub piece_layers: BTreeMap<[u8; 32], Cow<'a, [u8]>>,
+    pub url_list: Vec<Cow<'a, str>>,
+}
+
+impl<'a> TorrentRef<'a> {
+    pub fn from_bytes(src: &'a [u8]) -> Result<TorrentRef<'a>, TorrentFileError> {
+        TorrentRef::from_bytes_with_limits(src, DecodeLimits::default())
+    }
+
+    pub fn from_bytes_with_limits(
+        src: &'a [u8],
+        limits: DecodeLimits,
+    ) -> Result<TorrentRef<'a>, TorrentFileError> {
+        let torrent_builder = TorrentBuilder::new(src, limits);
+        torrent_builder.build()
+    }
+
+    fn is_valid(&self) -> Result<(), TorrentFileError> {
+        // a torrent with web seeds can be downloaded without any tracker
+        if self.announce.is_empty()
+            && self.announce_list.iter().all(Vec::is_empty)
+            && self.url_list.is_empty()
+        {
+            return Err(TorrentFileError::MissingRequiredKey {
+                state: TorrentBuilderStateKind::MetaInfo,
+                key: TorrentKey::Announce,
+            });
+        }
+        if self.info == InfoRef::default() {
+            return Err(TorrentFileError::MissingRequiredKey {
+                state: TorrentBuilderStateKind::MetaInfo,
+                key: TorrentKey::Info,
+            });
+        }
+
+        // a layer has one hash for each piece of its file, only files of a
+        // single piece go without one:
+        let piece_length = self.info.piece_length;
+        for file in self.info.file_tree.iter().flatten() {
+            if file.length <= piece_length {
+                continue;
+            }
+            let layer = file
+                .pieces_root
+                .and_then(|root| self.piece_layers.get(&root))
+                .ok_or(TorrentFileError::MissingRequiredKey {
+                    state: TorrentBuilderStateKind::MetaInfo,
+                    key: TorrentKey::PieceLayers,
+                })?;
+            let expected = usize::try_from(file.length.div_ceil(piece_length))
+                .ok()
+                .and_then(|count| count.checked_mul(32))
+                .ok_or(TorrentFileError::LengthOverflow)?;
+            if layer.len() != expected {
+                return Err(TorrentFileError::InvalidPieceLayer {
+                    expected,
+                    got: layer.len(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// The v1 info hash, or the v2 one for v2-only torrents.
+    pub fn info_hash(&self) -> Option<InfoHash> {
+        info_hash(
+            !self.info.pieces.is_empty(),
+            self.info.info_hash,
+            self.info.info_hash_v2,
+        )
+    }
+
+    /// Copies everything borrowed into a standalone [`Torrent`].
+    pub fn into_owned(self) -> Torrent {
+        Torrent {
+            announce: self.announce.into_owned(),
+            announce_list: self
+                .announce_list
+                .into_iter()
+                .map(|tier| tier.into_iter().map(Cow::into_owned).collect())
+                .collect(),
+            comment: self.comment.map(Cow::into_owned),
+            created_by: self.created_by.map(Cow::into_owned),
+            creation_date: self.creation_date,
+            encoding: self.encoding.map(Cow::into_owned),
+            info: self.info.into_owned(),
+            nodes: self
+                .nodes
+                .into_iter()
+                .map(|(host, port)| (host.into_owned(), port))
+                .collect(),
+            piece_layers: self
+                .piece_layers
+                .into_iter()
+                .map(|(root, layer)| (root, layer.into_owned()))
+                .collect(),
+            url_list: self.url_list.into_iter().map(Cow::into_owned).collect(),
+        }
+    }
+}
+
+#[derive(Default, Debug, PartialEq)]
+pub struct InfoRef<'a> {
+    pub info_hash: [u8; 20],
+
+    pub name: Cow<'a, str>,
+    pub name_raw: Cow<'a, [u8]>,
+    pub name_utf8: Option<Cow<'a, str>>,
+    pub piece_length: u64,
+    pub pieces: Cow<'a, [u8]>,
+    pub length: Option<u64>,
+    pub files: Option<Vec<FileRef<'a>>>,
+    pub private: bool,
+
+    pub meta_version: Option<i64>,
+    pub info_hash_v2: Option<[u8; 32]>,
+    pub file_tree: Option<Vec<TreeFileRef<'a>>>,
+
+    pub raw: Cow<'a, [u8]>,
+}
+
+impl<'a> InfoRef<'a> {
+    pub fn from_bytes(src: &'a [u8]) -> Result<InfoRef<'a>, TorrentFileError> {
+        TorrentBuilder::new(src, DecodeLimits::default()).build_info()
+    }
+
+    fn is_valid(&self) -> Result<(), TorrentFileError> {
+        if self.name.is_empty() {
+            return Err(TorrentFileError::MissingRequiredKey {
+                state: TorrentBuilderStateKind::Info,
+                key: TorrentKey::InfoName,
+            });
+        }
+
+        if self.piece_length == 0 {
+            return Err(TorrentFileError::MissingRequiredKey {
+                state: TorrentBuilderStateKind::Info,
+                key: TorrentKey::InfoPieceLength,
+            });
+        }
+
+        match self.meta_version {
+            None | Some(1) => {}
+            Some(2) => {
+                let Some(tree) = &self.file_tree else {
+                    return Err(TorrentFileError::MissingRequiredKey {
+                        state: TorrentBuilderStateKind::Info,
+                        key: TorrentKey::InfoFileTree,
+                    });
+                };
+                if !self.piece_length.is_power_of_two() || self.piece_length < MIN_V2_PIECE_LENGTH {
+                    return Err(TorrentFileError::InvalidPieceLength(self.piece_length));
+                }
+                tree.iter()
+                    .try_fold(0u64, |total, file| total.checked_add(file.length))
+                    .ok_or(TorrentFileError::LengthOverflow)?;
+                // v2-only torrents have no v1 keys to check:
+                if self.pieces.is_empty() && self.length.is_none() && self.files.is_none() {
+                    return Ok(());
+                }
+            }
+            Some(version) => return Err(TorrentFileError::UnsupportedMetaVersion(version)),
+        }
+
+        if self.pieces.is_empty() {
+            return Err(TorrentFileError::MissingRequiredKey {
+                state: TorrentBuilderStateKind::Info,
+                key: TorrentKey::InfoPieces,
+            });
+        }
+
+        if !self.pieces.len().is_multiple_of(20) {
+            return Err(TorrentFileError::InvalidPiecesLength(self.pieces.len()));
+        }
+
+        if self.length.is_none() && self.files.is_none() {
+            return Err(TorrentFileError::MissingRequiredKey {
+                state: TorrentBuilderStateKind::Info,
+                key: TorrentKey::InfoFiles,
+            });
+        }
+
+        let lengths: Vec<u64> = match (&self.files, self.length) {
+            (Some(files), _) => files.iter().map(|file| file.length as u64).collect(),
+            (None, length) => vec![length.unwrap_or_default()],
+        };
+        let layout = Layout::from_parts(self.piece_length, lengths, Cow::Borrowed(&self.pieces))?;
+        if layout.hashes().count() != layout.piece_count() {
+            return Err(TorrentFileError::InvalidPieceCount {
+                expected: layout.piece_count(),
+                got: layout.hashes().count(),
+            });
+        }
+
+        if let Some(tree) = &self.file_tree {
+            self.check_hybrid(tree)?;
+        }
+
+        Ok(())
+    }
+
+    /// Fills the readable names in from the raw ones, preferring the
+    /// `.utf-8` keys and decoding the rest with `encoding`.
+    fn decode_names(&mut self, encoding: Option<&str>) {
+        let encoding = encoding
+            .and_then(|label| Encoding::for_label(label.as_bytes()))
+            .unwrap_or(UTF_8);
+
+        self.name = match &self.name_utf8 {
+            Some(name) => name.clone(),
+            None => decode(&self.name_raw, encoding),
+        };
+        for file in self.files.iter_mut().flatten() {
+            file.path = match &file.path_utf8 {
+                Some(path) => path.clone(),
+                None => file
+                    .path_raw
+                    .iter()
+                    .map(|part| decode(part, encoding))
+                    .collect(),
+            };
+        }
+    }
+
+    /// The v1 files without padding must be the v2 tree files, in the same order.
+    fn check_hybrid(&self, tree: &[TreeFileRef]) -> Result<(), TorrentFileError> {
+        let v1: Vec<(&[Cow<str>], u64)> = match (&self.files, self.length) {
+            (Some(files), _) => files
+                .iter()
+                .filter(|file| !file.is_padding())
+                .map(|file| (file.path.as_slice(), file.length as u64))
+                .collect(),
+            (None, Some(length)) => vec![(std::slice::from_ref(&self.name), length)],
+            (None, None) => Vec::new(),
+        };
+
+        for (i, file) in tree.iter().enumerate() {
+            let same = v1
+                .get(i)
+                .is_some_and(|(path, length)| *path == file.path && *length == file.length);
+            if !same {
+                return Err(TorrentFileError::HybridMismatch(i));
+            }
+        }
+        if v1.len() != tree.len() {
+            return Err(TorrentFileError::HybridMismatch(tree.len()));
+        }
+        Ok(())
+    }
+
+    pub fn into_owned(self) -> Info {
+        Info {
+            info_hash: self.info_hash,
+            name: self.name.into_owned(),
+            name_raw: self.name_raw.into_owned(),
+            name_utf8: self.name_utf8.map(Cow::into_owned),
+            piece_length: self.piece_length,
+            pieces: self.pieces.into_owned(),
+            length: self.length,
+            files: self
+                .files
+                .map(|files| files.into_iter().map(FileRef::into_owned).collect()),
+            private: self.private,
+            meta_version: self.meta_version,
+            info_hash_v2: self.info_hash_v2,
+            file_tree: self
+                .file_tree
+                .map(|tree| tree.into_iter().map(TreeFileRef::into_owned).collect()),
+            raw: self.raw.into_owned(),
+        }
+    }
+}
+
+#[derive(Default, Debug, PartialEq)]
+pub struct TreeFileRef<'a> {
+    pub path: Vec<Cow<'a, str>>,
+    pub length: u64,
+    pub pieces_root: Option<[u8; 32]>,
+}
+
+impl TreeFileRef<'_> {
+    pub fn into_owned(self) -> TreeFile {
+        TreeFile {
+            path: self.path.into_iter().map(Cow::into_owned).collect(),
+            length: self.length,
+            pieces_root: self.pieces_root,
+        }
+    }
+}
+
+#[derive(Default, Debug, PartialEq)]
+pub struct FileRef<'a> {
+    pub length: usize,
+    pub path: Vec<Cow<'a, str>>,
+    pub path_raw: Vec<Cow<'a, [u8]>>,
+    pub path_utf8: Option<Vec<Cow<'a, str>>>,
+    pub attr: Option<Cow<'a, str>>,
+    pub symlink_path: Option<Vec<Cow<'a, str>>>,
+    pub sha1: Option<[u8; 20]>,
+}
+
+impl FileRef<'_> {
+    pub fn is_padding(&self) -> bool {
+        self.attr.as_ref().is_some_and(|attr| attr.contains('p'))
+    }
+
+    pub fn into_owned(self) -> File {
+        File {
+            length: self.length,
+            path: self.path.into_iter().map(Cow::into_owned).collect(),
+            path_raw: self.path_raw.into_iter().map(Cow::into_owned).collect(),
+            path_utf8: self
+                .path_utf8
+                .map(|path| path.into_iter().map(Cow::into_owned).collect()),
+            attr: self.attr.map(Cow::into_owned),
+            symlink_path: self
+                .symlink_path
+                .map(|path| path.into_iter().map(Cow::into_owned).collect()),
+            sha1: self.sha1,
+        }
+    }
+}
+
+/// A .torrent file mapped into memory, parsed on demand without copying.
+pub struct MappedTorrent {
+    mmap: Mmap,
+}
+
+impl MappedTorrent {
+    pub fn open(path: impl AsRef<Path>) -> Result<MappedTorrent, TorrentFileError> {
+        let file = fs::File::open(path)?;
+        // SAFETY: the mapping is read-only; like any mmap it assumes the file
+        // is not truncated or rewritten by someone else while it is mapped.
+        let mmap = unsafe { Mmap::map(&file)? };
+        Ok(MappedTorrent { mmap })
+    }
+
+    pub fn torrent(&self) -> Result<TorrentRef<'_>, TorrentFileError> {
+        TorrentRef::from_bytes(&self.mmap)
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.mmap
+    }
+}
+
+#[derive(Debug)]
+pub enum TorrentKey {
+    Announce,
+    AnnounceList,
+    Info,
+    InfoName,
+    InfoNameUtf8,
+    InfoPieceLength,
+    InfoPieces,
+    InfoLength,
+    InfoFiles,
+    FilesLength,
+    FilesPath,
+    FilesPathUtf8,
+    FilesAttr,
+    FilesSymlinkPath,
+    FilesSha1,
+    InfoPrivate,
+    InfoMetaVersion,
+    InfoFileTree,
+    FileTreeLength,
+    FileTreePiecesRoot,
+    PieceLayers,
+    UrlList,
+    Comment,
+    CreatedBy,
+    CreationDate,
+    Encoding,
+    Nodes,
+}
+
+impl Display for TorrentKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            TorrentKey::Announce => write!(f, "Announce"),
+            TorrentKey::AnnounceList => write!(f, "AnnounceList"),
+            TorrentKey::Info => write!(f, "Info"),
+            TorrentKey::InfoName => write!(f, "InfoName"),
+            TorrentKey::InfoNameUtf8 => write!(f, "InfoNameUtf8"),
+            TorrentKey::InfoPieceLength => write!(f, "InfoPieceLength"),
+            TorrentKey::InfoPieces => write!(f, "InfoPieces"),
+            TorrentKey::InfoLength => write!(f, "InfoLength"),
+            TorrentKey::InfoFiles => write!(f, "InfoFiles"),
+            TorrentKey::FilesLength => write!(f, "FilesLength"),
+            TorrentKey::FilesPath => write!(f, "FilesPath"),
+            TorrentKey::FilesPathUtf8 => write!(f, "FilesPathUtf8"),
+            TorrentKey::FilesAttr => write!(f, "FilesAttr"),
+            TorrentKey::FilesSymlinkPath => write!(f, "FilesSymlinkPath"),
+            TorrentKey::FilesSha1 => write!(f, "FilesSha1"),
+            TorrentKey::InfoPrivate => write!(f, "InfoPrivate"),
+            TorrentKey::InfoMetaVersion => write!(f, "InfoMetaVersion"),
+            TorrentKey::InfoFileTree => write!(f, "InfoFileTree"),
+            TorrentKey::FileTreeLength => write!(f, "FileTreeLength"),
+            TorrentKey::FileTreePiecesRoot => write!(f, "FileTreePiecesRoot"),
+            TorrentKey::PieceLayers => write!(f, "PieceLayers"),
+            TorrentKey::UrlList => write!(f, "UrlList"),
+            TorrentKey::Comment => write!(f, "Comment"),
+            TorrentKey::CreatedBy => write!(f, "CreatedBy"),
+            TorrentKey::CreationDate => write!(f, "CreationDate"),
+            TorrentKey::Encoding => write!(f, "Encoding"),
+            TorrentKey::Nodes => write!(f, "Nodes"),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum TorrentFileError {
+    #[error("error reading .torrent file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("error while decoding .torrent file: {0}")]
+    Decode(#[from] DecodeError),
+    #[error("expected one of the {state} keys, got {got}")]
+    ExpectedKey {
+        state: TorrentBuilderStateKind,
+        got: TokenKind,
+    },
+    #[error("{state} is missing {key}")]
+    MissingRequiredKey {
+        state: TorrentBuilderStateKind,
+        key: TorrentKey,
+    },
+    #[error("'files' and 'length' keys are mutually exclusive")]
+    MutualExclusiveKeys,
+    #[error(".torrent file is missing meta info opener")]
+    MissingMetaInfoOpener,
+    #[error("{key} key expected value of type {expected}, got {got}")]
+    UnexpectedTypeForKey {
+        key: TorrentKey,
+        expected: TokenKind,
+        got: TokenKind,
+    },
+    #[error("unexpected object closure")]
+    UnexpectedObjectClosure,
+    #[error("not valid UTF-8 when UTF-8 string is expected")]
+    Utf8(#[from] std::str::Utf8Error),
+    #[error("pieces has invalid length of {0} which is not divisible by 20")]
+    InvalidPiecesLength(usize),
+    #[error("pieces has hashes for {got} pieces, expected {expected} for the total length")]
+    InvalidPieceCount { expected: usize, got: usize },
+    #[error("limit exceeded: more than {0}")]
+    LimitExceeded(Limit),
+    #[error("limit exceeded: {key} must not be negative, got {value}")]
+    NegativeLength { key: TorrentKey, value: i64 },
+    #[error("limit exceeded: file lengths add up to more than {} bytes", u64::MAX)]
+    LengthOverflow,
+    #[error("meta version {0} is not supported")]
+    UnsupportedMetaVersion(i64),
+    #[error("hash has invalid length of {got}, expected {expected}")]
+    InvalidHashLength { expected: usize, got: usize },
+    #[error("piece length of {0} is not a power of two of at least 16 KiB")]
+    InvalidPieceLength(u64),
+    #[error("piece layer has invalid length of {got}, expected {expected}")]
+    InvalidPieceLayer { expected: usize, got: usize },
+    #[error("v1 files and v2 file tree of a hybrid torrent differ at file {0}")]
+    HybridMismatch(usize),
+    #[error("file tree has a file without a name at its root")]
+    UnnamedTreeFile,
+    #[error("{source} at offset {offset} ({path}): {excerpt}")]
+    At {
+        offset: usize,
+        path: TorrentPath,
+        excerpt: Excerpt,
+        source: Box<TorrentFileError>,
+    },
+}
+
+impl TorrentFileError {
+    /// Returns the error itself, without the location it happened at.
+    pub fn kind(&self) -> &TorrentFileError {
+        match self {
+            TorrentFileError::At { source, .. } => source.kind(),
+            e => e,
+        }
+    }
+
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            TorrentFileError::At { offset, .. } => Some(*offset),
+            _ => None,
+        }
+    }
+
+    pub fn path(&self) -> Option<&TorrentPath> {
+        match self {
+            TorrentFileError::At { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+}
+
+/// Logical location inside of a .torrent file, e.g. `info.files[12].path[3]`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TorrentPath(Vec<PathSegment>);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+impl TorrentPath {
+    pub fn segments(&self) -> &[PathSegment] {
+        &self.0
+    }
+
+    fn push_key(&mut self, key: &[u8]) {
+        self.0
+            .push(PathSegment::Key(String::from_utf8_lossy(key).into_owned()));
+    }
+
+    fn push_index(&mut self, i: usize) {
+        self.0.push(PathSegment::Index(i));
+    }
+
+    fn pop(&mut self) {
+        self.0.pop();
+    }
+}
+
+impl Display for TorrentPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.0.is_empty() {
+            return write!(f, "<root>");
+        }
+        for (i, segment) in self.0.iter().enumerate() {
+            match segment {
+                PathSegment::Key(key) if i == 0 => write!(f, "{key}")?,
+                PathSegment::Key(key) => write!(f, ".{key}")?,
+                PathSegment::Index(i) => write!(f, "[{i}]")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Torrent {
+    pub fn from_file(path: &str) -> Result<Torrent, TorrentFileError> {
+        let data = fs::read(path)?;
+        Torrent::from_bytes(&data)
+    }
+
+    pub fn from_bytes(src: &[u8]) -> Result<Torrent, TorrentFileError> {
+        Torrent::from_bytes_with_limits(src, DecodeLimits::default())
+    }
+
+    /// Same as [`Torrent::from_bytes`], but with custom bounds for untrusted input.
+    pub fn from_bytes_with_limits(
+        src: &[u8],
+        limits: DecodeLimits,
+    ) -> Result<Torrent, TorrentFileError> {
+        TorrentRef::from_bytes_with_limits(src, limits).map(TorrentRef::into_owned)
+    }
+
+    /// Tracker tiers to announce to: `announce-list` if it has any
+    /// trackers, otherwise a single tier with `announce` (BEP 12).
+    pub fn announce_tiers(&self) -> Vec<Vec<String>> {
+        let tiers: Vec<Vec<String>> = self
+            .announce_list
+            .iter()
+            .filter(|tier| !tier.is_empty())
+            .cloned()
+            .collect();
+
+        if tiers.is_empty() && !self.announce.is_empty() {
+            return vec![vec![self.announce.clone()]];
+        }
+        tiers
+    }
+
+    /// The v1 info hash, or the v2 one for v2-only torrents. Only its
+    /// [`InfoHash::truncated`] bytes go on the wire and to trackers.
+    pub fn info_hash(&self) -> Option<InfoHash> {
+        info_hash(
+            self.info.is_v1(),
+            self.info.info_hash,
+            self.info.info_hash_v2,
+        )
+    }
+
+    pub fn info_hash_v2(&self) -> Option<[u8; 32]> {
+        self.info.info_hash_v2
+    }
+
+    /// Every hash the torrent is known by: the v1 one and the v2 one, both
+    /// for hybrid torrents.
+    pub fn info_hashes(&self) -> Vec<InfoHash> {
+        let mut hashes = Vec::new();
+        if self.info.is_v1() {
+            hashes.push(InfoHash::V1(self.info.info_hash));
+        }
+        if let Some(v2) = self.info.info_hash_v2 {
+            hashes.push(InfoHash::V2(v2));
+        }
+        hashes
+    }
+
+    /// Encodes the torrent back into canonical bencode.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, EncodeError> {
+        let mut out = Vec::new();
+        self.write_to(&mut out)?;
+        Ok(out)
+    }
+
+    pub fn write_to<W: Write>(&self, out: W) -> Result<(), EncodeError> {
+        let mut enc = Encoder::new(out);
+        enc.begin_dict()?;
+
+        if !self.announce.is_empty() {
+            enc.str("announce")?;
+            enc.str(&self.announce)?;
+        }
+
+        if !self.announce_list.is_empty() {
+            enc.str("announce-list")?;
+            enc.begin_list()?;
+            for tier in &self.announce_list {
+                enc.begin_list()?;
+                for url in tier {
+                    enc.str(url)?;
+                }
+                enc.end()?;
+            }
+            enc.end()?;
+        }
+
+        if let Some(comment) = &self.comment {
+            enc.str("comment")?;
+            enc.str(comment)?;
+        }
+
+        if let Some(created_by) = &self.created_by {
+            enc.str("created by")?;
+            enc.str(created_by)?;
+        }
+
+        if let Some(date) = self.creation_date {
+            enc.str("creation date")?;
+            enc.int(date.timestamp())?;
+        }
+
+        if let Some(encoding) = &self.encoding {
+            enc.str("encoding")?;
+            enc.str(encoding)?;
+        }
+
+        enc.str("info")?;
+        self.info.encode(&mut enc)?;
+
+        if !self.nodes.is_empty() {
+            enc.str("nodes")?;
+            enc.begin_list()?;
+            for (host, port) in &self.nodes {
+                enc.begin_list()?;
+                enc.str(host)?;
+                enc.uint(*port as u64)?;
+                enc.end()?;
+            }
+            enc.end()?;
+        }
+
+        if !self.piece_layers.is_empty() {
+            enc.str("piece layers")?;
+            enc.begin_dict()?;
+            for (root, layer) in &self.piece_layers {
+                enc.bytes(root)?;
+                enc.bytes(layer)?;
+            }
+            enc.end()?;
+        }
+
+        if !self.url_list.is_empty() {
+            enc.str("url-list")?;
+            enc.begin_list()?;
+            for url in &self.url_list {
+                enc.str(url)?;
+            }
+            enc.end()?;
+        }
+
+        enc.end()?;
+        enc.into_inner()?;
+        Ok(())
+    }
+}
+
+impl Info {
+    /// Parses a bare info dict, e.g. the metadata fetched for a magnet link.
+    /// Both info hashes are computed over `src`.
+    pub fn from_bytes(src: &[u8]) -> Result<Info, TorrentFileError> {
+        InfoRef::from_bytes(src).map(InfoRef::into_owned)
+    }
+
+    /// Encodes the info dict into canonical bencode. For an info dict
+    /// without unknown keys, hashing these bytes gives back `info_hash`.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, EncodeError> {
+        let mut enc = Encoder::new(Vec::new());
+        self.encode(&mut enc)?;
+        enc.into_inner()
+    }
+
+    fn encode<W: Write>(&self, enc: &mut Encoder<W>) -> Result<(), EncodeError> {
+        enc.begin_dict()?;
+
+        if let Some(tree) = &self.file_tree {
+            enc.str("file tree")?;
+            encode_file_tree(tree, enc)?;
+        }
+
+        if let Some(files) = &self.files {
+            enc.str("files")?;
+            enc.begin_list()?;
+            for file in files {
+                file.encode(enc)?;
+            }
+            enc.end()?;
+        }
+
+        if let Some(length) = self.length {
+            enc.str("length")?;
+            enc.uint(length)?;
+        }
+
+        if let Some(version) = self.meta_version {
+            enc.str("meta version")?;
+            enc.int(version)?;
+        }
+
+        enc.str("name")?;
+        match self.name_raw.is_empty() {
+            true => enc.str(&self.name)?,
+            false => enc.bytes(&self.name_raw)?,
+        }
+
+        if let Some(name) = &self.name_utf8 {
+            enc.str("name.utf-8")?;
+            enc.str(name)?;
+        }
+
+        enc.str("piece length")?;
+        enc.uint(self.piece_length)?;
+
+        if !self.pieces.is_empty() {
+            enc.str("pieces")?;
+            enc.bytes(&self.pieces)?;
+        }
+
+        if self.private {
+            enc.str("private")?;
+            enc.int(1)?;
+        }
+
+        enc.end()
+    }
+}
+
+/// Nests the flattened files back into directories. The files must be in
+/// tree order, as they come from parsing.
+fn encode_file_tree<W: Write>(tree: &[TreeFile], enc: &mut Encoder<W>) -> Result<(), EncodeError> {
+    enc.begin_dict()?;
+    let mut dirs: &[String] = &[];
+
+    for file in tree {
+        let (name, parent) = file
+            .path
+            .split_last()
+            .map_or(("", &[][..]), |(n, p)| (n, p));
+
+        let common = dirs.iter().zip(parent).take_while(|(a, b)| a == b).count();
+        for _ in common..dirs.len() {
+            enc.end()?;
+        }
+        for dir in &parent[common..] {
+            enc.str(dir)?;
+            enc.begin_dict()?;
+        }
+        dirs = parent;
+
+        enc.str(name)?;
+        enc.begin_dict()?;
+        enc.str("")?;
+        enc.begin_dict()?;
+        enc.str("length")?;
+        enc.uint(file.length)?;
+        if let Some(root) = &file.pieces_root {
+            enc.str("pieces root")?;
+            enc.bytes(root)?;
+        }
+        enc.end()?;
+        enc.end()?;
+    }
+
+    for _ in 0..dirs.len() {
+        enc.end()?;
+    }
+    enc.end()
+}
+
+/// Lengths come as signed bencode ints, a negative one would wrap around.
+fn non_negative(key: TorrentKey, value: i64) -> Result<u64, TorrentFileError> {
+    u64::try_from(value).map_err(|_| TorrentFileError::NegativeLength { key, value })
+}
+
+fn info_hash(is_v1: bool, v1: [u8; 20], v2: Option<[u8; 32]>) -> Option<InfoHash> {
+    match v2 {
+        Some(v2) if !is_v1 => Some(InfoHash::V2(v2)),
+        _ => Some(InfoHash::V1(v1)),
+    }
+}
+
+impl File {
+    fn encode<W: Write>(&self, enc: &mut Encoder<W>) -> Result<(), EncodeError> {
+        enc.begin_dict()?;
+
+        if let Some(attr) = &self.attr {
+            enc.str("attr")?;
+            enc.str(attr)?;
+        }
+
+        enc.str("length")?;
+        enc.uint(self.length as u64)?;
+
+        enc.str("path")?;
+        enc.begin_list()?;
+        match self.path_raw.is_empty() {
+            true => self.path.iter().try_for_each(|part| enc.str(part))?,
+            false => self.path_raw.iter().try_for_each(|part| enc.bytes(part))?,
+        }
+        enc.end()?;
+
+        if let Some(path) = &self.path_utf8 {
+            enc.str("path.utf-8")?;
+            enc.begin_list()?;
+            for part in path {
+                enc.str(part)?;
+            }
+            enc.end()?;
+        }
+
+        if let Some(sha1) = &self.sha1 {
+            enc.str("sha1")?;
+            enc.bytes(sha1)?;
+        }
+
+        if let Some(target) = &self.symlink_path {
+            enc.str("symlink path")?;
+            enc.begin_list()?;
+            for part in target {
+                enc.str(part)?;
+            }
+            enc.end()?;
+        }
+
+        enc.end()
+    }
+}
+
+struct TorrentBuilder<'builder> {
+    state: TorrentBuilderState,
+    src: &'builder [u8],
+    info_begin: usize,
+    /// Parsing a bare info dict, e.g. one fetched from peers (BEP 9).
+    info_only: bool,
+    path: TorrentPath,
+    limits: DecodeLimits,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum TorrentBuilderState {
+    Begin,
+    MetaInfo,
+    Info,
+    Files,
+    SingularFile,
+    SingularFilePath,
+    Finished,
+}
+
+#[derive(Debug)]
+pub enum TorrentBuilderStateKind {
+    Begin,
+    MetaInfo,
+    Info,
+    Files,
+    SingularFile,
+    SingularFilePath,
+    Finished,
+}
+
+impl Display for TorrentBuilderStateKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            TorrentBuilderStateKind::Begin => write!(f, "Begin"),
+            TorrentBuilderStateKind::MetaInfo => write!(f, "MetaInfo"),
+            TorrentBuilderStateKind::Info => write!(f, "Info"),
+            TorrentBuilderStateKind::Files => write!(f, "Files"),
+            TorrentBuilderStateKind::SingularFile => write!(f, "SingularFile"),
+            TorrentBuilderStateKind::SingularFilePath => write!(f, "SingularFilePath"),
+            TorrentBuilderStateKind::Finished => write!(f, "Finished"),
+        }
+    }
+}
+
+impl From<TorrentBuilderState> for TorrentBuilderStateKind {
+    fn from(value: TorrentBuilderState) -> Self {
+        match value {
+            TorrentBuilderState::Begin => TorrentBuilderStateKind::Begin,
+            TorrentBuilderState::MetaInfo => TorrentBuilderStateKind::MetaInfo,
+            TorrentBuilderState::Info => TorrentBuilderStateKind::Info,
+            TorrentBuilderState::Files => TorrentBuilderStateKind::Files,
+            TorrentBuilderState::SingularFile => TorrentBuilderStateKind::SingularFile,
+            TorrentBuilderState::SingularFilePath => TorrentBuilderStateKind::SingularFilePath,
+            TorrentBuilderState::Finished => TorrentBuilderStateKind::Finished,
+        }
+    }
+}
+
+impl Display for TorrentBuilderState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            TorrentBuilderState::Begin => write!(f, "Begin"),
+            TorrentBuilderState::MetaInfo => write!(f, "MetaInfo"),
+            TorrentBuilderState::Info => write!(f, "Info"),
+            TorrentBuilderState::Files => write!(f, "Files"),
+            TorrentBuilderState::SingularFile => write!(f, "SingularFile"),
+            TorrentBuilderState::SingularFilePath => write!(f, "SingularFilePath"),
+            TorrentBuilderState::Finished => write!(f, "Finished"),
+        }
+    }
+}
+
+impl<'builder> TorrentBuilder<'builder> {
+    fn new(src: &'builder [u8], limits: DecodeLimits) -> TorrentBuilder<'builder> {
+        TorrentBuilder {
+            state: TorrentBuilderState::Begin,
+            info_begin: 0,
+            info_only: false,
+            src,
+            path: TorrentPath::default(),
+            limits,
+        }
+    }
+
+    fn build(mut self) -> Result<TorrentRef<'builder>, TorrentFileError> {
+        let mut dec = Decoder::with_options(
+            self.src,
+            DecoderOptions {
+                limits: self.limits,
+                ..Default::default()
+            },
+        );
+
+        let mut torrent = TorrentRef::default();
+
+        match self.run(&mut dec, &mut torrent) {
+            Ok(()) => Ok(torrent),
+            Err(e) => Err(self.locate(e, &dec)),
+        }
+    }
+
+    fn build_info(mut self) -> Result<InfoRef<'builder>, TorrentFileError> {
+        self.info_only = true;
+        self.build().map(|torrent| torrent.info)
+    }
+
+    /// Attaches the offset, the logical path and the bytes around to the error.
+    fn locate(&mut self, e: TorrentFileError, dec: &Decoder) -> TorrentFileError {
+        let offset = match e {
+            // the failed token was not consumed:
+            TorrentFileError::Decode(_) => dec.pos(),
+            _ => dec.token_pos(),
+        };
+
+        TorrentFileError::At {
+            offset,
+            path: std::mem::take(&mut self.path),
+            excerpt: Excerpt::new(self.src, offset),
+            source: Box::new(e),
+        }
+    }
+
+    fn run(
+        &mut self,
+        dec: &mut Decoder<'builder>,
+        torrent: &mut TorrentRef<'builder>,
+    ) -> Result<(), TorrentFileError> {
+        loop {
+            match self.state {
+                TorrentBuilderState::Begin => match dec.next_token()? {
+                    Token::BeginDict(i) if self.info_only => {
+                        self.info_begin = i;
+                        self.state = TorrentBuilderState::Info
+                    }
+                    Token::BeginDict(_) => self.state = TorrentBuilderState::MetaInfo,
+                    _ => return Err(TorrentFileError::MissingMetaInfoOpener),
+                },
+
+                TorrentBuilderState::MetaInfo => {
+                    let token = dec.next_token()?;
+                    match token {
+                        Token::String(key) => {
+                            self.path.push_key(&key);
+                            self.handle_meta_keys(key, dec, torrent)?;
+                            self.pop_scalar_key(TorrentBuilderState::MetaInfo);
+                        }
+
+                        // stepping out of the root, we're done:
+                        Token::EndObject(_) => self.state = TorrentBuilderState::Finished,
+
+                        _ => {
+                            return Err(TorrentFileError::ExpectedKey {
+                                state: self.state.into(),
+                                got: token.into(),
+                            });
+                        }
+                    };
+                }
+
+                TorrentBuilderState::Info => {
+                    let token = dec.next_token()?;
+                    match token {
+                        Token::String(key) => {
+                            self.path.push_key(&key);
+                            self.handle_info_keys(key, dec, torrent)?;
+                            self.pop_scalar_key(TorrentBuilderState::Info);
+                        }
+
+                        // stepping back by one state:
+                        Token::EndObject(pos) => {
+                            let slice = self.get_info_slice(pos);
+                            torrent.info.info_hash = make_sha1(slice);
+                            if torrent.info.meta_version == Some(2) {
+                                torrent.info.info_hash_v2 = Some(make_sha256(slice));
+                            }
+                            torrent.info.raw = Cow::Borrowed(slice);
+                            // `encoding` sorts before `info`, so it's known by now
+                            // unless the keys are out of order, see `Finished`
+                            torrent.info.decode_names(torrent.encoding.as_deref());
+                            torrent.info.is_valid()?;
+                            self.path.pop();
+                            self.state = match self.info_only {
+                                true => TorrentBuilderState::Finished,
+                                false => TorrentBuilderState::MetaInfo,
+                            };
+                        }
+
+                        _ => {
+                            return Err(TorrentFileError::ExpectedKey {
+                                state: self.state.into(),
+                                got: token.into(),
+                            });
+                        }
+                    };
+                }
+
+                TorrentBuilderState::Files => {
+                    if torrent.info.files.is_none() {
+                        torrent.info.files = Some(Vec::new());
+                    }
+
+                    let token = dec.next_token()?;
+                    match token {
+                        Token::BeginDict(_) => {
+                            self.state = TorrentBuilderState::SingularFile;
+                            let files = torrent.info.files.as_mut().unwrap();
+                            if files.len() >= self.limits.max_files {
+                                return Err(TorrentFileError::LimitExceeded(Limit::Files(
+                                    self.limits.max_files,
+                                )));
+                            }
+                            self.path.push_index(files.len());
+                            files.push(FileRef::default());
+                        }
+
+                        // stepping back by one state:
+                        Token::EndObject(_) => {
+                            self.path.pop();
+                            self.state = TorrentBuilderState::Info
+                        }
+
+                        _ => {
+                            return Err(TorrentFileError::ExpectedKey {
+                                state: self.state.into(),
+                                got: token.into(),
+                            });
+                        }
+                    };
+                }
+
+                TorrentBuilderState::SingularFile => {
+                    let token = dec.next_token()?;
+                    match token {
+                        Token::String(key) => {
+                            self.path.push_key(&key);
+                            self.handle_file_keys(key, dec, torrent)?;
+                            self.pop_scalar_key(TorrentBuilderState::SingularFile);
+                        }
+
+                        // stepping back by one state:
+                        Token::EndObject(_) => {
+                            self.path.pop();
+                            self.state = TorrentBuilderState::Files
+                        }
+
+                        _ => {
+                            return Err(TorrentFileError::ExpectedKey {
+                                state: self.state.into(),
+                                got: token.into(),
+                            });
+                        }
+                    }
+                }
+
+                TorrentBuilderState::SingularFilePath => {
+                    let token = dec.next_token()?;
+                    if !matches!(token, Token::EndObject(_)) {
+                        let files = torrent.info.files.as_ref().unwrap();
+                        self.path
+                            .push_index(files.last().map_or(0, |f| f.path_raw.len()));
+                    }
+
+                    match token {
+                        Token::String(path) => {
+                            self.handle_file_path(path, torrent)?;
+                            self.path.pop();
+                        }
+
+                        // stepping back by one state:
+                        Token::EndObject(_) => {
+                            self.path.pop();
+                            self.state = TorrentBuilderState::SingularFile
+                        }
+
+                        _ => {
+                            return Err(TorrentFileError::UnexpectedTypeForKey {
+                                key: TorrentKey::FilesPath,
+                                expected: TokenKind::String,
+                                got: token.into(),
+                            });
+                        }
+                    }
+                }
+
+                TorrentBuilderState::Finished => {
+                    if !self.info_only {
+                        // the raw names are kept, an `encoding` that came after
+                        // `info` still applies to them
+                        if torrent.encoding.is_some() {
+                            torrent.info.decode_names(torrent.encoding.as_deref());
+                        }
+                        torrent.is_valid()?;
+                    }
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Keys of nested values stay on the path until the value is closed.
+    fn pop_scalar_key(&mut self, state: TorrentBuilderState) {
+        if self.state == state {
+            self.path.pop();
+        }
+    }
+
+    fn handle_meta_keys(
+        &mut self,
+        key: Cow<'builder, [u8]>,
+        dec: &mut Decoder<'builder>,
+        torrent: &mut TorrentRef<'builder>,
+    ) -> Result<(), TorrentFileError> {
+        match &*key {
+            b"announce" => self.handle_announce(dec, torrent),
+            b"announce-list" => self.handle_announce_list(dec, torrent),
+            b"piece layers" => self.handle_piece_layers(dec, torrent),
+            b"url-list" => self.handle_url_list(dec, torrent),
+            b"comment" => {
+                torrent.comment = Some(extract_str(dec, TorrentKey::Comment)?);
+                Ok(())
+            }
+            b"created by" => {
+                torrent.created_by = Some(extract_str(dec, TorrentKey::CreatedBy)?);
+                Ok(())
+            }
+            b"creation date" => {
+                let date =
+                    expect_extract(dec, TorrentKey::CreationDate, TokenKind::Int, |t| match t {
+                        Token::Int(i) => Some(*i),
+                        _ => None,
+                    })?;
+                // purely informational, a date out of range is dropped
+                torrent.creation_date = DateTime::from_timestamp(date, 0);
+                Ok(())
+            }
+            b"encoding" => {
+                torrent.encoding = Some(extract_str(dec, TorrentKey::Encoding)?);
+                Ok(())
+            }
+            b"nodes" => self.handle_nodes(dec, torrent),
+
+            b"info" => {
+                self.state = TorrentBuilderState::Info;
+
+                self.info_begin =
+                    expect_extract(dec, TorrentKey::Info, TokenKind::BeginDict, |t| match t {
+                        Token::BeginDict(i) => Some(*i),
+                        _ => None,
+                    })?;
+                Ok(())
+            }
+
+            _ => self.skip_value(dec),
+        }
+    }
+
+    fn handle_announce(
+        &self,
+        dec: &mut Decoder<'builder>,
+        torrent: &mut TorrentRef<'builder>,
+    ) -> Result<(), TorrentFileError> {
+        let announce_url =
+            expect_extract(dec, TorrentKey::Announce, TokenKind::String, |t| match t {
+                Token::String(cow) => Some(cow.clone()),
+                _ => None,
+            })?;
+
+        torrent.announce = utf8(announce_url)?;
+        Ok(())
+    }
+
+    fn handle_announce_list(
+        &mut self,
+        dec: &mut Decoder<'builder>,
+        torrent: &mut TorrentRef<'builder>,
+    ) -> Result<(), TorrentFileError> {
+        expect_token(dec, TorrentKey::AnnounceList, TokenKind::BeginList, |t| {
+            matches!(t, Token::BeginList(_))
+        })?;
+
+        loop {
+            self.path.push_index(torrent.announce_list.len());
+            let token = dec.next_token()?;
+            match token {
+                Token::BeginList(_) => {}
+                Token::EndObject(_) => {
+                    self.path.pop();
+                    return Ok(());
+                }
+                _ => {
+                    return Err(TorrentFileError::UnexpectedTypeForKey {
+                        key: TorrentKey::AnnounceList,
+                        expected: TokenKind::BeginList,
+                        got: token.into(),
+                    });
+                }
+            }
+
+            let mut tier = Vec::new();
+            loop {
+                self.path.push_index(tier.len());
+                let token = dec.next_token()?;
+                match token {
+                    Token::String(url) => tier.push(utf8(url)?),
+                    Token::EndObject(_) => {
+                        self.path.pop();
+                        break;
+                    }
+                    _ => {
+                        return Err(TorrentFileError::UnexpectedTypeForKey {
+                            key: TorrentKey::AnnounceList,
+                            expected: TokenKind::String,
+                            got: token.into(),
+                        });
+                    }
+                }
+                self.path.pop();
+            }
+
+            torrent.announce_list.push(tier);
+            self.path.pop();
+        }
+    }
+
+    /// A single web seed may come as a bare string instead of a list.
+    fn handle_url_list(
+        &mut self,
+        dec: &mut Decoder<'builder>,
+        torrent: &mut TorrentRef<'builder>,
+    ) -> Result<(), TorrentFileError> {
+        let token = dec.next_token()?;
+        match token {
+            Token::String(url) => {
+                if !url.is_empty() {
+                    torrent.url_list.push(utf8(url)?);
+                }
+                return Ok(());
+            }
+            Token::BeginList(_) => {}
+            _ => {
+                return Err(TorrentFileError::UnexpectedTypeForKey {
+                    key: TorrentKey::UrlList,
+                    expected: TokenKind::BeginList,
+                    got: token.into(),
+                });
+            }
+        }
+
+        loop {
+            self.path.push_index(torrent.url_list.len());
+            let token = dec.next_token()?;
+            match token {
+                Token::String(url) if url.is_empty() => {}
+                Token::String(url) => torrent.url_list.push(utf8(url)?),
+                Token::EndObject(_) => {
+                    self.path.pop();
+                    return Ok(());
+                }
+                _ => {
+                    return Err(TorrentFileError::UnexpectedTypeForKey {
+                        key: TorrentKey::UrlList,
+                        expected: TokenKind::String,
+                        got: token.into(),
+                    });
+                }
+            }
+            self.path.pop();
+        }
+    }
+
+    /// Each node is a `[host, port]` list.
+    fn handle_nodes(
+        &mut self,
+        dec: &mut Decoder<'builder>,
+        torrent: &mut TorrentRef<'builder>,
+    ) -> Result<(), TorrentFileError> {
+        expect_token(dec, TorrentKey::Nodes, TokenKind::BeginList, |t| {
+            matches!(t, Token::BeginList(_))
+        })?;
+
+        loop {
+            self.path.push_index(torrent.nodes.len());
+            let token = dec.next_token()?;
+            match token {
+                Token::BeginList(_) => {}
+                Token::EndObject(_) => {
+                    self.path.pop();
+                    return Ok(());
+                }
+                _ => {
+                    return Err(TorrentFileError::UnexpectedTypeForKey {
+                        key: TorrentKey::Nodes,
+                        expected: TokenKind::BeginList,
+                        got: token.into(),
+                    });
+                }
+            }
+
+            let host = extract_str(dec, TorrentKey::Nodes)?;
+            let port = expect_extract(dec, TorrentKey::Nodes, TokenKind::Int, |t| match t {
+                Token::Int(i) => u16::try_from(*i).ok(),
+                _ => None,
+            })?;
+            expect_token(dec, TorrentKey::Nodes, TokenKind::EndObject, |t| {
+                matches!(t, Token::EndObject(_))
+            })?;
+
+            torrent.nodes.push((host, port));
+            self.path.pop();
+        }
+    }
+
+    fn handle_piece_layers(
+        &mut self,
+        dec: &mut Decoder<'builder>,
+        torrent: &mut TorrentRef<'builder>,
+    ) -> Result<(), TorrentFileError> {
+        expect_token(dec, TorrentKey::PieceLayers, TokenKind::BeginDict, |t| {
+            matches!(t, Token::BeginDict(_))
+        })?;
+
+        loop {
+            let token = dec.next_token()?;
+            let root = match token {
+                Token::String(root) => hash(&root)?,
+                Token::EndObject(_) => return Ok(()),
+                _ => {
+                    return Err(TorrentFileError::ExpectedKey {
+                        state: self.state.into(),
+                        got: token.into(),
+                    });
+                }
+            };
+
+            let layer = expect_extract(
+                dec,
+                TorrentKey::PieceLayers,
+                TokenKind::String,
+                |t| match t {
+                    Token::String(cow) => Some(cow.clone()),
+                    _ => None,
+                },
+            )?;
+            torrent.piece_layers.insert(root, layer);
+        }
+    }
+
+    /// Reads a directory of the `file tree`, its opening is already consumed.
+    /// Recursion is bounded by the decoder's depth limit.
+    fn handle_file_tree_dir(
+        &mut self,
+        dec: &mut Decoder<'builder>,
+        dir: &mut Vec<Cow<'builder, str>>,
+        files: &mut Vec<TreeFileRef<'builder>>,
+    ) -> Result<(), TorrentFileError> {
+        loop {
+            let token = dec.next_token()?;
+            let name = match token {
+                Token::String(name) => name,
+                Token::EndObject(_) => return Ok(()),
+                _ => {
+                    return Err(TorrentFileError::UnexpectedTypeForKey {
+                        key: TorrentKey::InfoFileTree,
+                        expected: TokenKind::String,
+                        got: token.into(),
+                    });
+                }
+            };
+
+            self.path.push_key(&name);
+            expect_token(dec, TorrentKey::InfoFileTree, TokenKind::BeginDict, |t| {
+                matches!(t, Token::BeginDict(_))
+            })?;
+
+            // an empty key holds the properties of the file named by `dir`,
+            // at the root there is no such file
+            if name.is_empty() && dir.is_empty() {
+                return Err(TorrentFileError::UnnamedTreeFile);
+            }
+            if name.is_empty() {
+                if files.len() >= self.limits.max_files {
+                    return Err(TorrentFileError::LimitExceeded(Limit::Files(
+                        self.limits.max_files,
+                    )));
+                }
+                let file = self.handle_file_tree_file(dec, dir)?;
+                files.push(file);
+            } else {
+                dir.push(utf8(name)?);
+                self.handle_file_tree_dir(dec, dir, files)?;
+                dir.pop();
+            }
+            self.path.pop();
+        }
+    }
+
+    fn handle_file_tree_file(
+        &mut self,
+        dec: &mut Decoder<'builder>,
+        dir: &[Cow<'builder, str>],
+    ) -> Result<TreeFileRef<'builder>, TorrentFileError> {
+        let mut file = TreeFileRef {
+            path: dir.to_vec(),
+            ..Default::default()
+        };
+        let mut has_length = false;
+
+        loop {
+            let token = dec.next_token()?;
+            let key = match token {
+                Token::String(key) => key,
+                Token::EndObject(_) => break,
+                _ => {
+                    return Err(TorrentFileError::ExpectedKey {
+                        state: self.state.into(),
+                        got: token.into(),
+                    });
+                }
+            };
+
+            self.path.push_key(&key);
+            match &*key {
+                b"length" => {
+                    let length = expect_extract(
+                        dec,
+                        TorrentKey::FileTreeLength,
+                        TokenKind::Int,
+                        |t| match t {
+                            Token::Int(i) => Some(*i),
+                            _ => None,
+                        },
+                    )?;
+                    file.length = non_negative(TorrentKey::FileTreeLength, length)?;
+                    has_length = true;
+                }
+                b"pieces root" => {
+                    let root = expect_extract(
+                        dec,
+                        TorrentKey::FileTreePiecesRoot,
+                        TokenKind::String,
+                        |t| match t {
+                            Token::String(cow) => Some(cow.clone()),
+                            _ => None,
+                        },
+                    )?;
+                    file.pieces_root = Some(hash(&root)?);
+                }
+                _ => self.skip_value(dec)?,
+            }
+            self.path.pop();
+        }
+
+        if !has_length {
+            return Err(TorrentFileError::MissingRequiredKey {
+                state: self.state.into(),
+                key: TorrentKey::FileTreeLength,
+            });
+        }
+        if file.length > 0 && file.pieces_root.is_none() {
+            return Err(TorrentFileError::MissingRequiredKey {
+                state: self.state.into(),
+                key: TorrentKey::FileTreePiecesRoot,
+            });
+        }
+        Ok(file)
+    }
+
+    fn get_info_slice(&self, end_pos: usize) -> &'builder [u8] {
+        &self.src[self.info_begin..end_pos + 1]
+    }
+
+    fn handle_info_keys(
+        &mut self,
+        key: Cow<'builder, [u8]>,
+        dec: &mut Decoder<'builder>,
+        torrent: &mut TorrentRef<'builder>,
+    ) -> Result<(), TorrentFileError> {
+        match &*key {
+            b"name" => self.handle_name(dec, torrent),
+            b"name.utf-8" => {
+                torrent.info.name_utf8 = Some(extract_str(dec, TorrentKey::InfoNameUtf8)?);
+                Ok(())
+            }
+            b"piece length" => self.handle_piece_length(dec, torrent),
+            b"pieces" => self.handle_pieces(dec, torrent),
+            b"private" => {
+                let private =
+                    expect_extract(dec, TorrentKey::InfoPrivate, TokenKind::Int, |t| match t {
+                        Token::Int(i) => Some(*i),
+                        _ => None,
+                    })?;
+                // anything but 1 is a public torrent
+                torrent.info.private = private == 1;
+                Ok(())
+            }
+            b"meta version" => {
+                let version = expect_extract(
+                    dec,
+                    TorrentKey::InfoMetaVersion,
+                    TokenKind::Int,
+                    |t| match t {
+                        Token::Int(i) => Some(*i),
+                        _ => None,
+                    },
+                )?;
+                torrent.info.meta_version = Some(version);
+                Ok(())
+            }
+            b"file tree" => {
+                expect_token(dec, TorrentKey::InfoFileTree, TokenKind::BeginDict, |t| {
+                    matches!(t, Token::BeginDict(_))
+                })?;
+
+                let mut files = Vec::new();
+                self.handle_file_tree_dir(dec, &mut Vec::new(), &mut files)?;
+                torrent.info.file_tree = Some(files);
+                Ok(())
+            }
+            b"length" => {
+                if torrent.info.files.is_some() {
+                    return Err(TorrentFileError::MutualExclusiveKeys);
+                }
+                self.handle_length(dec, torrent)
+            }
+            b"files" => {
+                if torrent.info.length.is_some() {
+                    return Err(TorrentFileError::MutualExclusiveKeys);
+                }
+                self.state = TorrentBuilderState::Files;
+
+                expect_token(dec, TorrentKey::InfoFiles, TokenKind::BeginList, |t| {
+                    matches!(t, Token::BeginList(_))
+                })
+            }
+            _ => self.skip_value(dec),
+        }
+    }
+
+    fn handle_name(
+        &self,
+        dec: &mut Decoder<'builder>,
+        torrent: &mut TorrentRef<'builder>,
+    ) -> Result<(), TorrentFileError> {
+        let name = expect_extract(dec, TorrentKey::InfoName, TokenKind::String, |t| match t {
+            Token::String(cow) => Some(cow.clone()),
+            _ => None,
+        })?;
+
+        // legacy torrents have names in other charsets, decoded later
+        torrent.info.name_raw = name;
+        Ok(())
+    }
+
+    fn handle_piece_length(
+        &self,
+        dec: &mut Decoder<'builder>,
+        torrent: &mut TorrentRef<'builder>,
+    ) -> Result<(), TorrentFileError> {
+        let piece_length =
+            expect_extract(dec, TorrentKey::InfoPieceLength, TokenKind::Int, |t| match t {
+                Token::Int(i) => Some(*i),
+                _ => None,
+            })?;
+
+        torrent.info.piece_length = non_negative(TorrentKey::InfoPieceLength, piece_length)?;
+        Ok(())
+    }
+
+    fn handle_pieces(
+        &self,
+        dec: &mut Decoder<'builder>,
+        torrent: &mut TorrentRef<'builder>,
+    ) -> Result<(), TorrentFileError> {
+        let pieces = expect_extract(
+            dec,
+            TorrentKey::InfoPieces,
+            TokenKind::String,
+            |t| match t {
+                Token::String(cow) => Some(cow.clone()),
+                _ => None,
+            },
+        )?;
+
+        torrent.info.pieces = pieces;
+        Ok(())
+    }
+
+    fn handle_length(
+        &self,
+        dec: &mut Decoder<'builder>,
+        torrent: &mut TorrentRef<'builder>,
+    ) -> Result<(), TorrentFileError> {
+        let len = expect_extract(dec, TorrentKey::InfoLength, TokenKind::Int, |t| match t {
+            Token::Int(i) => Some(*i),
+            _ => None,
+        })?;
+
+        torrent.info.length = Some(non_negative(TorrentKey::InfoLength, len)?);
+        Ok(())
+    }
+
+    fn handle_file_keys(
+        &mut self,
+        key: Cow<'builder, [u8]>,
+        dec: &mut Decoder<'builder>,
+        torrent: &mut TorrentRef<'builder>,
+    ) -> Result<(), TorrentFileError> {
+        match &*key {
+            b"length" => self.handle_file_length(dec, torrent),
+
+            b"attr" => {
+                let attr =
+                    expect_extract(dec, TorrentKey::FilesAttr, TokenKind::String, |t| match t {
+                        Token::String(cow) => Some(cow.clone()),
+                        _ => None,
+                    })?;
+                let files = torrent.info.files.as_mut().unwrap();
+                files.last_mut().unwrap().attr = Some(utf8(attr)?);
+                Ok(())
+            }
+
+            b"sha1" => {
+                let sha1 =
+                    expect_extract(dec, TorrentKey::FilesSha1, TokenKind::String, |t| match t {
+                        Token::String(cow) => Some(cow.clone()),
+                        _ => None,
+                    })?;
+                let files = torrent.info.files.as_mut().unwrap();
+                files.last_mut().unwrap().sha1 = Some(hash(&sha1)?);
+                Ok(())
+            }
+
+            b"symlink path" => {
+                let target = self.handle_str_list(dec, TorrentKey::FilesSymlinkPath)?;
+                let files = torrent.info.files.as_mut().unwrap();
+                files.last_mut().unwrap().symlink_path = Some(target);
+                Ok(())
+            }
+
+            b"path.utf-8" => {
+                let path = self.handle_str_list(dec, TorrentKey::FilesPathUtf8)?;
+                let files = torrent.info.files.as_mut().unwrap();
+                files.last_mut().unwrap().path_utf8 = Some(path);
+                Ok(())
+            }
+
+            b"path" => {
+                self.state = TorrentBuilderState::SingularFilePath;
+
+                expect_token(dec, TorrentKey::FilesPath, TokenKind::BeginList, |t| {
+                    matches!(t, Token::BeginList(_))
+                })
+            }
+
+            _ => self.skip_value(dec),
+        }
+    }
+
+    /// A list of UTF-8 strings, e.g. the components of a path.
+    fn handle_str_list(
+        &mut self,
+        dec: &mut Decoder<'builder>,
+        key: TorrentKey,
+    ) -> Result<Vec<Cow<'builder, str>>, TorrentFileError> {
+        let token = dec.next_token()?;
+        if !matches!(token, Token::BeginList(_)) {
+            return Err(TorrentFileError::UnexpectedTypeForKey {
+                key,
+                expected: TokenKind::BeginList,
+                got: token.into(),
+            });
+        }
+
+        let mut list = Vec::new();
+        loop {
+            self.path.push_index(list.len());
+            let token = dec.next_token()?;
+            match token {
+                Token::String(part) => list.push(utf8(part)?),
+                Token::EndObject(_) => {
+                    self.path.pop();
+                    return Ok(list);
+                }
+                _ => {
+                    return Err(TorrentFileError::UnexpectedTypeForKey {
+                        key,
+                        expected: TokenKind::String,
+                        got: token.into(),
+                    });
+                }
+            }
+            self.path.pop();
+        }
+    }
+
+    fn handle_file_length(
+        &self,
+        dec: &mut Decoder<'builder>,
+        torrent: &mut TorrentRef<'builder>,
+    ) -> Result<(), TorrentFileError> {
+        let length = expect_extract(dec, TorrentKey::FilesLength, TokenKind::Int, |t| match t {
+            Token::Int(i) => Some(*i),
+            _ => None,
+        })?;
+        let length = non_negative(TorrentKey::FilesLength, length)? as usize;
+
+        let files = torrent.info.files.as_mut().unwrap();
+        match files.last_mut() {
+            Some(file) => file.length = length,
+            None => {
+                files.push(FileRef {
+                    length,
+                    ..Default::default()
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_file_path(
+        &self,
+        path: Cow<'builder, [u8]>,
+        torrent: &mut TorrentRef<'builder>,
+    ) -> Result<(), TorrentFileError> {
+        let files = torrent.info.files.as_mut().unwrap();
+        match files.last_mut() {
+            Some(file) => file.path_raw.push(path),
+            None => {
+                let mut file = FileRef::default();
+                file.path_raw.push(path);
+                files.push(file);
+            }
+        }
+        Ok(())
+    }
+
+    /// USE IT ONLY TO SKIP VALUES OF UNKNOWN KEYS
+    fn skip_value(&self, dec: &mut Decoder) -> Result<(), TorrentFileError> {
+        match dec.next_token()? {
+            Token::Int(_) | Token::String(_) => Ok(()), // already skipped
+            Token::EndObject(_) => Err(TorrentFileError::UnexpectedObjectClosure),
+            Token::BeginDict(_) | Token::BeginList(_) => self.skip_nested_value(dec),
+        }
+    }
+
+    /// USE IT ONLY TO SKIP VALUES OF UNKNOWN KEYS
+    fn skip_nested_value(&self, dec: &mut Decoder) -> Result<(), TorrentFileError> {
+        let mut counter = 1; // already have 1 opened object
+
+        while counter != 0 {
+            match dec.next_token()? {
+                Token::Int(_) | Token::String(_) => continue,
+                Token::BeginDict(_) | Token::BeginList(_) => counter += 1,
+                Token::EndObject(_) => counter -= 1,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn hash<const N: usize>(bytes: &[u8]) -> Result<[u8; N], TorrentFileError> {
+    bytes
+        .try_into()
+        .map_err(|_| TorrentFileError::InvalidHashLength {
+            expected: N,
+            got: bytes.len(),
+        })
+}
+
+fn extract_str<'a>(
+    dec: &mut Decoder<'a>,
+    key: TorrentKey,
+) -> Result<Cow<'a, str>, TorrentFileError> {
+    let s = expect_extract(dec, key, TokenKind::String, |t| match t {
+        Token::String(cow) => Some(cow.clone()),
+        _ => None,
+    })?;
+    Ok(utf8(s)?)
+}
+
+/// Borrows whenever the bytes need no conversion.
+fn decode<'a>(raw: &Cow<'a, [u8]>, encoding: &'static Encoding) -> Cow<'a, str> {
+    match raw {
+        Cow::Borrowed(raw) => encoding.decode_without_bom_handling(raw).0,
+        Cow::Owned(raw) => Cow::Owned(encoding.decode_without_bom_handling(raw).0.into_owned()),
+    }
+}
+
+fn utf8(bytes: Cow<'_, [u8]>) -> Result<Cow<'_, str>, std::str::Utf8Error> {
+    match bytes {
+        Cow::Borrowed(b) => std::str::from_utf8(b).map(Cow::Borrowed),
+        Cow::Owned(b) => String::from_utf8(b)
+            .map(Cow::Owned)
+            .map_err(|e| e.utf8_error()),
+    }
+}
+
+fn expect_token<F>(
+    dec: &mut Decoder,
+    key: TorrentKey,
+    expected: TokenKind,
+    ok: F,
+) -> Result<(), TorrentFileError>
+where
+    F: FnOnce(&Token) -> bool,
+{
+    let token = dec.next_token()?;
+    if ok(&token) {
+        Ok(())
+    } else {
+        Err(TorrentFileError::UnexpectedTypeForKey {
+            key,
+            expected,
+            got: token.into(),
+        })
+    }
+}
+
+fn expect_extract<'a, T>(
+    dec: &mut Decoder<'a>,
+    key: TorrentKey,
+    expected: TokenKind,
+    f: impl FnOnce(&Token<'a>) -> Option<T>,
+) -> Result<T, TorrentFileError> {
+    let token = dec.next_token()?;
+    if let Some(v) = f(&token) {
+        Ok(v)
+    } else {
+        Err(TorrentFileError::UnexpectedTypeForKey {
+            key,
+            expected,
+            got: token.into(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test_torrent {
+
+    use std::fs;
+
+    use super::*;
+
+    fn concat(parts: &[&[u8]]) -> Vec<u8> {
+        parts.concat()
+    }
+
+    #[test]
+    fn valid_bep_003_single_file_torrent() {
+        let mut data = fs::read("../test_data/fixtures/single_bep_003.torrent")
+            .expect("file must be opened and read");
+
+        let res = Torrent::from_bytes(&mut data);
+        assert!(res.is_ok(), "unexpected error: {:?}", res.err().unwrap());
+    }
+
+    #[test]
+    fn valid_bep_003_multi_file_torrent() {
+        let mut data = fs::read("../test_data/fixtures/multi_bep_003.torrent")
+            .expect("file must be opened and read");
+
+        Torrent::from_bytes(&mut data).unwrap();
+    }
+
+    #[test]
+    fn get_info_slice_returns_info_dict_bytes() {
+        let info_bytes = concat(&[
+            b"d",
+            b"4:name4:test",
+            b"12:piece lengthi16384e",
+            b"6:pieces20:12345678901234567890",
+            b"6:lengthi123e",
+            b"e",
+        ]);
+
+        let data = concat(&[
+            b"d",
+            b"8:announce14:http://tracker",
+            b"4:info",
+            info_bytes.as_slice(),
+            b"e",
+        ]);
+
+        let mut builder = TorrentBuilder::new(&data, DecodeLimits::default());
+        let info_begin = data.len() - info_bytes.len() - 1;
+        builder.info_begin = info_begin;
+
+        let end_pos = info_begin + info_bytes.len() - 1;
+        assert_eq!(builder.get_info_slice(end_pos), info_bytes.as_slice());
+    }
+
+    #[test]
+    fn unknown_root_keys_are_ignored() {
+        let data = concat(&[
+            b"d",
+            b"8:announce14:http://tracker",
+            b"5:extra",
+            b"l",
+            b"i1e",
+            b"i2e",
+            b"e",
+            b"4:infod",
+            b"4:name4:test",
+            b"12:piece lengthi16384e",
+            b"6:pieces20:12345678901234567890",
+            b"6:lengthi123e",
+            b"e",
+            b"e",
+        ]);
+
+        let res = Torrent::from_bytes(&data);
+        assert!(res.is_ok(), "unexpected error: {:?}", res.err().unwrap());
+    }
+
+    #[test]
+    fn unknown_info_keys_are_ignored() {
+        let data = concat(&[
+            b"d",
+            b"8:announce14:http://tracker",
+            b"4:infod",
+            b"4:name4:test",
+            b"12:piece lengthi16384e",
+            b"6:pieces20:12345678901234567890",
+            b"6:lengthi123e",
+            b"3:extd3:foo3:baree",
+            b"e",
+            b"e",
+        ]);
+
+        let res = Torrent::from_bytes(&data);
+        assert!(res.is_ok(), "unexpected error: {:?}", res.err().unwrap());
+    }
+
+    #[test]
+    fn unknown_file_keys_are_ignored() {
+        let data = concat(&[
+            b"d",
+            b"8:announce14:http://tracker",
+            b"4:infod",
+            b"4:name4:test",
+            b"12:piece lengthi16384e",
+            b"6:pieces20:12345678901234567890",
+            b"5:filesl",
+            b"d",
+            b"6:lengthi10e",
+            b"4:pathl3:foo3:bare",
+            b"5:extrad3:fooi1ee",
+            b"e",
+            b"e",
+            b"e",
+            b"e",
+        ]);
+
+        let res = Torrent::from_bytes(&data);
+        assert!(res.is_ok(), "unexpected error: {:?}", res.err().unwrap());
+    }
+
+    #[test]
+    fn error_on_files_and_length_in_info() {
+        let data = concat(&[
+            b"d",
+            b"8:announce14:http://tracker",
+            b"4:infod",
+            b"4:name4:test",
+            b"12:piece lengthi16384e",
+            b"6:pieces20:12345678901234567890",
+            b"6:lengthi123e",
+            b"5:filesle",
+            b"e",
+            b"e",
+        ]);
+
+        let err = Torrent::from_bytes(&data).unwrap_err();
+        assert!(matches!(err.kind(), TorrentFileError::MutualExclusiveKeys));
+    }
+
+    #[test]
+    fn error_on_invalid_announce_type() {
+        let data = concat(&[
+            b"d",
+            b"8:announcei1e",
+            b"4:infod",
+            b"4:name4:test",
+            b"12:piece lengthi16384e",
+            b"6:pieces20:12345678901234567890",
+            b"6:lengthi123e",
+            b"e",
+            b"e",
+        ]);
+
+        let err = Torrent::from_bytes(&data).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            TorrentFileError::UnexpectedTypeForKey {
+                key: TorrentKey::Announce,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn error_on_invalid_file_path_type() {
+        let data = concat(&[
+            b"d",
+            b"8:announce14:http://tracker",
+            b"4:infod",
+            b"4:name4:test",
+            b"12:piece lengthi16384e",
+            b"6:pieces20:12345678901234567890",
+            b"5:filesl",
+            b"d",
+            b"6:lengthi10e",
+            b"4:pathi1e",
+            b"e",
+            b"e",
+            b"e",
+            b"e",
+        ]);
+
+        let err = Torrent::from_bytes(&data).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            TorrentFileError::UnexpectedTypeForKey {
+                key: TorrentKey::FilesPath,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn error_on_missing_info_dict() {
+        let data = concat(&[b"d", b"8:announce14:http://tracker", b"e"]);
+
+        let res = Torrent::from_bytes(&data);
+        assert!(res.is_err(), "expected error for missing info dict");
+    }
+
+    #[test]
+    fn error_on_missing_pieces() {
+        let data = concat(&[
+            b"d",
+            b"8:announce14:http://tracker",
+            b"4:infod",
+            b"4:name4:test",
+            b"12:piece lengthi16384e",
+            b"6:lengthi123e",
+            b"e",
+            b"e",
+        ]);
+
+        let res = Torrent::from_bytes(&data);
+        assert!(res.is_err(), "expected error for missing pieces");
+    }
+
+    #[test]
+    fn error_on_length_overflow() {
+        let file = b"d6:lengthi9223372036854775807e4:pathl1:aee";
+        let data = concat(&[
+            b"d",
+            b"8:announce14:http://tracker",
+            b"4:infod",
+            b"5:filesl",
+            file,
+            file,
+            file,
+            b"e",
+            b"4:name4:test",
+            b"12:piece lengthi16384e",
+            b"6:pieces20:",
+            &[0; 20],
+            b"e",
+            b"e",
+        ]);
+
+        let err = Torrent::from_bytes(&data).unwrap_err();
+        assert!(matches!(err.kind(), TorrentFileError::LengthOverflow));
+    }
+
+    #[test]
+    fn error_on_wrong_piece_count() {
+        let torrent = |length: &[u8]| {
+            concat(&[
+                b"d",
+                b"8:announce14:http://tracker",
+                b"4:infod",
+                b"6:length",
+                length,
+                b"4:name4:test",
+                b"12:piece lengthi16384e",
+                b"6:pieces40:",
+                &[0; 40],
+                b"e",
+                b"e",
+            ])
+        };
+
+        let err = Torrent::from_bytes(&torrent(b"i16384e")).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            TorrentFileError::InvalidPieceCount {
+                expected: 1,
+                got: 2
+            }
+        ));
+
+        let torrent = Torrent::from_bytes(&torrent(b"i16385e")).unwrap();
+        let layout = torrent.info.layout();
+        assert_eq!(layout.piece_count(), 2);
+        assert_eq!(layout.last_piece_len(), 1);
+        assert_eq!(layout.hashes().count(), 2);
+    }
+
+    #[test]
+    fn reencoded_torrent_keeps_info_hash() {
+        let data = concat(&[
+            b"d",
+            b"8:announce14:http://tracker",
+            b"4:infod",
+            b"5:filesl",
+            b"d6:lengthi10e4:pathl3:foo3:baree",
+            b"d6:lengthi20e4:pathl3:bazee",
+            b"e",
+            b"4:name4:test",
+            b"12:piece lengthi16384e",
+            b"6:pieces20:12345678901234567890",
+            b"e",
+            b"e",
+        ]);
+
+        let torrent = Torrent::from_bytes(&data).unwrap();
+        let encoded = torrent.to_bytes().unwrap();
+        assert_eq!(encoded, data);
+
+        let info = torrent.info.to_bytes().unwrap();
+        assert_eq!(make_sha1(&info), torrent.info.info_hash);
+    }
+
+    #[test]
+    fn bare_info_dict_parses() {
+        let info =
+            b"d6:lengthi10e4:name4:test12:piece lengthi16384e6:pieces20:12345678901234567890e";
+
+        let parsed = Info::from_bytes(info).unwrap();
+        assert_eq!(parsed.raw, info);
+        assert_eq!(parsed.name, "test");
+        assert_eq!(parsed.length, Some(10));
+        assert_eq!(parsed.info_hash, make_sha1(info));
+
+        let torrent = Torrent::from_bytes(&concat(&[b"d8:announce1:x4:info", info, b"e"])).unwrap();
+        assert_eq!(torrent.info, parsed);
+
+        assert!(Info::from_bytes(b"d4:name4:teste").is_err());
+    }
+
+    #[test]
+    fn private_flag() {
+        let info = |private: &[u8]| {
+            let info = concat(&[
+                b"d6:lengthi10e4:name4:test12:piece lengthi16384e6:pieces20:12345678901234567890",
+                private,
+                b"e",
+            ]);
+            Info::from_bytes(&info).map(|info| info.private)
+        };
+
+        assert!(info(b"7:privatei1e").unwrap());
+        assert!(!info(b"7:privatei0e").unwrap());
+        assert!(!info(b"").unwrap());
+        assert!(info(b"7:private1:1").is_err());
+    }
+
+    #[test]
+    fn error_reports_offset_and_path() {
+        let data = concat(&[
+            b"d",
+            b"8:announce14:http://tracker",
+            b"4:infod",
+            b"5:filesl",
+            b"d6:lengthi10e4:pathl3:fooee",
+            b"d6:lengthi10e4:pathl3:fooi1eee",
+            b"e",
+            b"e",
+            b"e",
+        ]);
+
+        let err = Torrent::from_bytes(&data).unwrap_err();
+        assert_eq!(err.path().unwrap().to_string(), "info.files[1].path[1]");
+        assert_eq!(err.offset(), Some(95));
+        assert_eq!(data[95], b'i');
+        assert!(err.to_string().contains("info.files[1].path[1]"));
+    }
+
+    #[test]
+    fn decode_error_reports_offset_and_path() {
+        let data = concat(&[
+            b"d",
+            b"8:announce14:http://tracker",
+            b"4:infod",
+            b"4:name4:test",
+            b"12:piece lengthi16x84e",
+        ]);
+
+        let err = Torrent::from_bytes(&data).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            TorrentFileError::Decode(DecodeError::WrongSyntax)
+        ));
+        assert_eq!(err.path().unwrap().to_string(), "info.piece length");
+        assert_eq!(err.offset(), Some(62));
+        assert_eq!(data[62], b'i');
+    }
+
+    #[test]
+    fn missing_key_error_reports_path() {
+        let data = concat(&[
+            b"d",
+            b"8:announce14:http://tracker",
+            b"4:infod",
+            b"4:name4:test",
+            b"12:piece lengthi16384e",
+            b"6:lengthi123e",
+            b"e",
+            b"e",
+        ]);
+
+        let err = Torrent::from_bytes(&data).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            TorrentFileError::MissingRequiredKey {
+                key: TorrentKey::InfoPieces,
+                ..
+            }
+        ));
+        assert_eq!(err.path().unwrap().to_string(), "info");
+    }
+
+    #[test]
+    fn error_on_too_many_files() {
+        let data = concat(&[
+            b"d",
+            b"8:announce14:http://tracker",
+            b"4:infod",
+            b"5:filesl",
+            b"d6:lengthi1e4:pathl1:aee",
+            b"d6:lengthi1e4:pathl1:bee",
+            b"e",
+            b"4:name4:test",
+            b"12:piece lengthi16384e",
+            b"6:pieces20:aaaaaaaaaaaaaaaaaaaa",
+            b"e",
+            b"e",
+        ]);
+        let limits = DecodeLimits {
+            max_files: 1,
+            ..Default::default()
+        };
+
+        Torrent::from_bytes(&data).unwrap();
+        let err = Torrent::from_bytes_with_limits(&data, limits).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            TorrentFileError::LimitExceeded(Limit::Files(1))
+        ));
+        assert_eq!(err.offset(), Some(67));
+        assert_eq!(err.path().unwrap().to_string(), "info.files");
+    }
+
+    #[test]
+    fn error_on_too_many_tree_files() {
+        let data = v2_torrent(&[b'x'; 64]);
+        let limits = DecodeLimits {
+            max_files: 2,
+            ..Default::default()
+        };
+
+        let err = Torrent::from_bytes_with_limits(&data, limits).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            TorrentFileError::LimitExceeded(Limit::Files(2))
+        ));
+    }
+
+    #[test]
+    fn error_on_negative_length() {
+        let torrent = |length: &[u8], piece_length: &[u8]| {
+            concat(&[
+                b"d",
+                b"8:announce14:http://tracker",
+                b"4:infod",
+                b"5:filesl",
+                b"d6:length",
+                length,
+                b"4:pathl1:aee",
+                b"d6:length",
+                length,
+                b"4:pathl1:bee",
+                b"e",
+                b"4:name4:test",
+                b"12:piece length",
+                piece_length,
+                b"6:pieces20:aaaaaaaaaaaaaaaaaaaa",
+                b"e",
+                b"e",
+            ])
+        };
+
+        let err = Torrent::from_bytes(&torrent(b"i-1e", b"i16384e")).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            TorrentFileError::NegativeLength {
+                key: TorrentKey::FilesLength,
+                value: -1
+            }
+        ));
+        let err = Torrent::from_bytes(&torrent(b"i1e", b"i-16384e")).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            TorrentFileError::NegativeLength {
+                key: TorrentKey::InfoPieceLength,
+                value: -16384
+            }
+        ));
+    }
+
+    #[test]
+    fn error_on_too_long_string() {
+        let data = concat(&[
+            b"d",
+            b"8:announce14:http://tracker",
+            b"4:infod",
+            b"4:name4:test",
+            b"e",
+            b"e",
+        ]);
+        let limits = DecodeLimits {
+            max_string_len: 8,
+            ..Default::default()
+        };
+
+        let err = Torrent::from_bytes_with_limits(&data, limits).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            TorrentFileError::Decode(DecodeError::LimitExceeded {
+                offset: 11,
+                limit: Limit::StringLength(8),
+            })
+        ));
+        assert_eq!(err.path().unwrap().to_string(), "announce");
+    }
+
+    fn multi_file_torrent() -> Vec<u8> {
+        concat(&[
+            b"d",
+            b"8:announce14:http://tracker",
+            b"4:infod",
+            b"5:filesl",
+            b"d6:lengthi1e4:pathl3:dir1:aee",
+            b"d6:lengthi2e4:pathl1:bee",
+            b"e",
+            b"4:name4:test",
+            b"12:piece lengthi16384e",
+            b"6:pieces20:aaaaaaaaaaaaaaaaaaaa",
+            b"e",
+            b"e",
+        ])
+    }
+
+    #[test]
+    fn torrent_ref_borrows_from_input() {
+        let data = multi_file_torrent();
+        let torrent = TorrentRef::from_bytes(&data).unwrap();
+
+        assert!(matches!(torrent.announce, Cow::Borrowed("http://tracker")));
+        assert!(matches!(torrent.info.name, Cow::Borrowed("test")));
+        assert!(matches!(torrent.info.pieces, Cow::Borrowed(_)));
+
+        let files = torrent.info.files.as_ref().unwrap();
+        assert_eq!(files[0].path, ["dir", "a"]);
+        assert!(matches!(files[1].path[0], Cow::Borrowed("b")));
+    }
+
+    #[test]
+    fn torrent_ref_into_owned_matches_torrent() {
+        let data = multi_file_torrent();
+        let owned = TorrentRef::from_bytes(&data).unwrap().into_owned();
+        let torrent = Torrent::from_bytes(&data).unwrap();
+
+        assert_eq!(owned.announce, torrent.announce);
+        assert_eq!(owned.info, torrent.info);
+    }
+
+    #[test]
+    fn mapped_torrent_parses_file() {
+        let data = multi_file_torrent();
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(&data).unwrap();
+
+        let mapped = MappedTorrent::open(file.path()).unwrap();
+        let torrent = mapped.torrent().unwrap();
+
+        assert_eq!(mapped.bytes(), data.as_slice());
+        assert_eq!(
+            torrent.info_hash(),
+            Some(InfoHash::V1(make_sha1(&data[34..data.len() - 1])))
+        );
+    }
+
+    #[test]
+    fn announce_list_is_parsed_into_tiers() {
+        let data = concat(&[
+            b"d",
+            b"13:announce-listll5:http1e",
+            b"l5:http25:http3ee",
+            b"4:infod",
+            b"6:lengthi123e",
+            b"4:name4:test",
+            b"12:piece lengthi16384e",
+            b"6:pieces20:aaaaaaaaaaaaaaaaaaaa",
+            b"e",
+            b"e",
+        ]);
+
+        let torrent = Torrent::from_bytes(&data).unwrap();
+        assert!(torrent.announce.is_empty());
+        assert_eq!(
+            torrent.announce_list,
+            [vec!["http1"], vec!["http2", "http3"]]
+        );
+        assert_eq!(torrent.announce_tiers(), torrent.announce_list);
+        assert_eq!(torrent.to_bytes().unwrap(), data);
+    }
+
+    #[test]
+    fn announce_is_the_only_tier_without_announce_list() {
+        let torrent = Torrent::from_bytes(&multi_file_torrent()).unwrap();
+
+        assert_eq!(torrent.announce_tiers(), [vec!["http://tracker"]]);
+    }
+
+    #[test]
+    fn error_on_invalid_announce_list() {
+        let data = concat(&[
+            b"d",
+            b"8:announce14:http://tracker",
+            b"13:announce-listll5:http1i1eee",
+            b"e",
+        ]);
+
+        let err = Torrent::from_bytes(&data).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            TorrentFileError::UnexpectedTypeForKey {
+                key: TorrentKey::AnnounceList,
+                expected: TokenKind::String,
+                got: TokenKind::Int,
+            }
+        ));
+        assert_eq!(err.path().unwrap().to_string(), "announce-list[0][1]");
+    }
+
+    #[test]
+    fn url_list_is_a_string_or_a_list() {
+        let mut data = multi_file_torrent();
+        data.pop();
+        let single = concat(&[&data, b"8:url-list15:http://seed/one", b"e"]);
+        let list = concat(&[&data, b"8:url-listl15:http://seed/one0:15:http://seed/twoe", b"e"]);
+
+        let torrent = Torrent::from_bytes(&single).unwrap();
+        assert_eq!(torrent.url_list, ["http://seed/one"]);
+
+        let torrent = Torrent::from_bytes(&list).unwrap();
+        assert_eq!(torrent.url_list, ["http://seed/one", "http://seed/two"]);
+
+        let reencoded = Torrent::from_bytes(&torrent.to_bytes().unwrap()).unwrap();
+        assert_eq!(reencoded.url_list, torrent.url_list);
+    }
+
+    fn v2_info() -> Vec<u8> {
+        concat(&[
+            b"d",
+            b"9:file treed",
+            b"3:dird",
+            b"1:ad0:d6:lengthi10e11:pieces root32:",
+            &[b'A'; 32],
+            b"ee",
+            b"1:bd0:d6:lengthi0eee",
+            b"e",
+            b"4:rootd0:d6:lengthi20000e11:pieces root32:",
+            &[b'B'; 32],
+            b"ee",
+            b"e",
+            b"12:meta versioni2e",
+            b"4:name4:test",
+            b"12:piece lengthi16384e",
+            b"e",
+        ])
+    }
+
+    fn v2_torrent(layer: &[u8]) -> Vec<u8> {
+        concat(&[
+            b"d",
+            b"8:announce14:http://tracker",
+            b"4:info",
+            &v2_info(),
+            b"12:piece layersd32:",
+            &[b'B'; 32],
+            format!("{}:", layer.len()).as_bytes(),
+            layer,
+            b"e",
+            b"e",
+        ])
+    }
+
+    #[test]
+    fn v2_only_torrent() {
+        let data = v2_torrent(&[b'x'; 64]);
+        let torrent = Torrent::from_bytes(&data).unwrap();
+
+        let tree = torrent.info.file_tree.as_ref().unwrap();
+        let paths: Vec<_> = tree.iter().map(|f| f.path.join("/")).collect();
+        assert_eq!(paths, ["dir/a", "dir/b", "root"]);
+        assert_eq!(tree[0].pieces_root, Some([b'A'; 32]));
+        assert_eq!(tree[1].pieces_root, None);
+        assert_eq!(torrent.total_length(), 20010);
+        assert_eq!(torrent.piece_layers[&[b'B'; 32]], [b'x'; 64]);
+
+        let v2 = make_sha256(&v2_info());
+        assert!(!torrent.info.is_v1());
+        assert_eq!(torrent.info_hash_v2(), Some(v2));
+        assert_eq!(torrent.info_hash(), Some(InfoHash::V2(v2)));
+    }
+
+    #[test]
+    fn v2_torrent_reencodes_to_same_bytes() {
+        let data = v2_torrent(&[b'x'; 64]);
+        let torrent = Torrent::from_bytes(&data).unwrap();
+
+        assert_eq!(torrent.to_bytes().unwrap(), data);
+    }
+
+    #[test]
+    fn error_on_wrong_piece_layer_length() {
+        let data = v2_torrent(&[b'x'; 32]);
+
+        let err = Torrent::from_bytes(&data).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            TorrentFileError::InvalidPieceLayer {
+                expected: 64,
+                got: 32
+            }
+        ));
+    }
+
+    #[test]
+    fn error_on_missing_piece_layer() {
+        let data = concat(&[b"d8:announce14:http://tracker4:info", &v2_info(), b"e"]);
+
+        let err = Torrent::from_bytes(&data).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            TorrentFileError::MissingRequiredKey {
+                key: TorrentKey::PieceLayers,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn error_on_malformed_file_tree() {
+        let error_with = |from: &str, to: &str| {
+            let data = String::from_utf8(v2_torrent(&[b'x'; 64])).unwrap();
+            Torrent::from_bytes(data.replace(from, to).as_bytes()).unwrap_err()
+        };
+
+        let err = error_with("9:file treed", "9:file treed0:d6:lengthi1ee");
+        assert!(matches!(err.kind(), TorrentFileError::UnnamedTreeFile));
+
+        let err = error_with(&format!("11:pieces root32:{}", "A".repeat(32)), "");
+        assert!(matches!(
+            err.kind(),
+            TorrentFileError::MissingRequiredKey {
+                key: TorrentKey::FileTreePiecesRoot,
+                ..
+            }
+        ));
+
+        let err = error_with("11:pieces root32:", "11:pieces root31:");
+        assert!(matches!(
+            err.kind(),
+            TorrentFileError::InvalidHashLength {
+                expected: 32,
+                got: 31
+            }
+        ));
+    }
+
+    #[test]
+    fn error_on_bad_v2_lengths() {
+        let error_with = |lengths: &[(&str, &str)]| {
+            let data = String::from_utf8(v2_torrent(&[b'x'; 64])).unwrap();
+            let data = lengths.iter().fold(data, |data, (from, to)| {
+                data.replace(&format!("length{from}"), &format!("length{to}"))
+            });
+            Torrent::from_bytes(data.as_bytes()).unwrap_err()
+        };
+
+        let err = error_with(&[("i20000e", "i-1e")]);
+        assert!(matches!(
+            err.kind(),
+            TorrentFileError::NegativeLength {
+                key: TorrentKey::FileTreeLength,
+                value: -1
+            }
+        ));
+
+        for piece_length in ["i1e", "i8192e", "i20000e"] {
+            let err = error_with(&[("i16384e", piece_length)]);
+            assert!(matches!(
+                err.kind(),
+                TorrentFileError::InvalidPieceLength(_)
+            ));
+        }
+
+        let max = "i9223372036854775807e";
+        // the empty file needs a root once it's not empty
+        let with_root = format!("{max}11:pieces root32:{}", "C".repeat(32));
+        let err = error_with(&[("i10e", max), ("i0e", &with_root), ("i20000e", max)]);
+        assert!(matches!(err.kind(), TorrentFileError::LengthOverflow));
+    }
+
+    #[test]
+    fn v2_pieces_are_counted_per_file() {
+        let info = Info::from_bytes(&v2_info()).unwrap();
+        assert_eq!(info.piece_count(), 3);
+
+        let huge = || TreeFile {
+            length: u64::MAX,
+            ..Default::default()
+        };
+        let info = Info {
+            piece_length: 1,
+            file_tree: Some(vec![huge(), huge()]),
+            ..Default::default()
+        };
+        assert_eq!(info.piece_count(), usize::MAX);
+        assert_eq!(info.total_length(), u64::MAX);
+    }
+
+    #[test]
+    fn error_on_unsupported_meta_version() {
+        let data = concat(&[
+            b"d",
+            b"8:announce14:http://tracker",
+            b"4:infod",
+            b"6:lengthi1e",
+            b"12:meta versioni3e",
+            b"4:name4:test",
+            b"12:piece lengthi16384e",
+            b"6:pieces20:aaaaaaaaaaaaaaaaaaaa",
+            b"e",
+            b"e",
+        ]);
+
+        let err = Torrent::from_bytes(&data).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            TorrentFileError::UnsupportedMetaVersion(3)
+        ));
+    }
+
+    fn hybrid_torrent(root_length: &[u8]) -> Vec<u8> {
+        concat(&[
+            b"d",
+            b"8:announce14:http://tracker",
+            b"4:infod",
+            b"9:file treed",
+            b"1:ad0:d6:lengthi10e11:pieces root32:",
+            &[b'A'; 32],
+            b"ee",
+            b"1:bd0:d6:lengthi20000e11:pieces root32:",
+            &[b'B'; 32],
+            b"ee",
+            b"e",
+            b"5:filesl",
+            b"d6:lengthi10e4:pathl1:aee",
+            b"d4:attr1:p6:lengthi16374e4:pathl4:.pad5:16374ee",
+            b"d6:length",
+            root_length,
+            b"4:pathl1:bee",
+            b"e",
+            b"12:meta versioni2e",
+            b"4:name4:test",
+            b"12:piece lengthi16384e",
+            b"6:pieces60:",
+            &[b'a'; 60],
+            b"e",
+            b"12:piece layersd32:",
+            &[b'B'; 32],
+            b"64:",
+            &[b'L'; 64],
+            b"e",
+            b"e",
+        ])
+    }
+
+    #[test]
+    fn informational_keys() {
+        let data = concat(&[
+            b"d8:announce14:http://tracker7:comment5:hello10:created by5:tcore",
+            b"13:creation datei1700000000e8:encoding5:UTF-8",
+            b"4:infod6:lengthi1e4:name4:test12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaae",
+            b"5:nodesll9:127.0.0.1i6881eel3:::1i1eeee",
+        ]);
+        let torrent = Torrent::from_bytes(&data).unwrap();
+
+        assert_eq!(torrent.comment.as_deref(), Some("hello"));
+        assert_eq!(torrent.created_by.as_deref(), Some("tcore"));
+        assert_eq!(
+            torrent.creation_date,
+            DateTime::from_timestamp(1_700_000_000, 0)
+        );
+        assert_eq!(torrent.encoding.as_deref(), Some("UTF-8"));
+        assert_eq!(
+            torrent.nodes,
+            [("127.0.0.1".to_string(), 6881), ("::1".to_string(), 1)]
+        );
+        assert_eq!(torrent.to_bytes().unwrap(), data);
+    }
+
+    #[test]
+    fn error_on_malformed_node() {
+        let data = |nodes: &[u8]| {
+            concat(&[
+                b"d8:announce14:http://tracker",
+                b"4:infod6:lengthi1e4:name4:test12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaae",
+                b"5:nodesl",
+                nodes,
+                b"ee",
+            ])
+        };
+
+        assert!(Torrent::from_bytes(&data(b"l4:hosti6881ee")).is_ok());
+        for node in [&b"4:host"[..], b"l4:hostee", b"l4:hosti70000ee", b"l4:hosti1ei2ee"] {
+            assert!(matches!(
+                Torrent::from_bytes(&data(node)).unwrap_err().kind(),
+                TorrentFileError::UnexpectedTypeForKey {
+                    key: TorrentKey::Nodes,
+                    ..
+                }
+            ));
+        }
+    }
+
+    #[test]
+    fn legacy_names_are_decoded() {
+        // "Привет" in CP1251 and "日本" in Shift_JIS
+        let cp1251 = [0xcf, 0xf0, 0xe8, 0xe2, 0xe5, 0xf2];
+        let shift_jis = [0x93, 0xfa, 0x96, 0x7b];
+        let info = |encoding: &[u8], utf8_keys: &[u8]| {
+            concat(&[
+                b"d8:announce14:http://tracker",
+                encoding,
+                b"4:infod5:filesld6:lengthi1e4:pathl4:",
+                &shift_jis,
+                b"e",
+                utf8_keys,
+                b"ee4:name6:",
+                &cp1251,
+                b"12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaaee",
+            ])
+        };
+
+        let data = info(b"8:encoding12:windows-1251", b"");
+        let torrent = Torrent::from_bytes(&data).unwrap();
+        assert_eq!(torrent.info.name, "Привет");
+        assert_eq!(torrent.info.name_raw, cp1251);
+        let file = &torrent.info.files.as_ref().unwrap()[0];
+        assert_eq!(file.path_raw(), [shift_jis.to_vec()]);
+        assert_eq!(torrent.to_bytes().unwrap(), data);
+
+        // out of order, after `info`
+        let mut data = info(b"", b"");
+        data.pop();
+        let data = concat(&[&data, b"8:encoding12:windows-1251e"]);
+        let torrent = Torrent::from_bytes(&data).unwrap();
+        assert_eq!(torrent.info.name, "Привет");
+
+        let data = info(b"", "10:path.utf-8l6:日本e".as_bytes());
+        let torrent = Torrent::from_bytes(&data).unwrap();
+        assert_eq!(torrent.info.name, "\u{fffd}".repeat(6));
+        assert_eq!(torrent.info.files.as_ref().unwrap()[0].path(), ["日本"]);
+        assert_eq!(torrent.to_bytes().unwrap(), data);
+        assert_eq!(make_sha1(&torrent.info.to_bytes().unwrap()), torrent.info.info_hash);
+    }
+
+    #[test]
+    fn name_utf8_is_preferred() {
+        let info = concat(&[
+            b"d6:lengthi1e4:name2:",
+            &[0xff, 0xfe],
+            b"10:name.utf-84:test12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaae",
+        ]);
+
+        let parsed = Info::from_bytes(&info).unwrap();
+        assert_eq!(parsed.name, "test");
+        assert_eq!(parsed.name_raw, [0xff, 0xfe]);
+        assert_eq!(parsed.to_bytes().unwrap(), info);
+    }
+
+    #[test]
+    fn file_attributes() {
+        let data = concat(&[
+            b"d8:announce14:http://tracker4:infod5:filesl",
+            b"d4:attr2:xh6:lengthi1e4:pathl3:rune4:sha120:",
+            &[b's'; 20],
+            b"e",
+            b"d4:attr1:l6:lengthi0e4:pathl4:linke12:symlink pathl3:dir3:runee",
+            b"e4:name4:test12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaaee",
+        ]);
+        let torrent = Torrent::from_bytes(&data).unwrap();
+        let files = torrent.info.files.as_ref().unwrap();
+
+        assert!(files[0].is_executable() && files[0].is_hidden());
+        assert!(!files[0].is_symlink());
+        assert_eq!(files[0].sha1(), Some([b's'; 20]));
+        assert!(files[1].is_symlink());
+        assert_eq!(files[1].symlink_path(), Some(&["dir".into(), "run".into()][..]));
+        assert_eq!(torrent.to_bytes().unwrap(), data);
+
+        let short_sha1 = concat(&[
+            b"d8:announce14:http://tracker4:infod5:filesl",
+            b"d6:lengthi1e4:pathl3:rune4:sha119:",
+            &[b's'; 19],
+            b"ee4:name4:test12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaaee",
+        ]);
+        let err = Torrent::from_bytes(&short_sha1).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            TorrentFileError::InvalidHashLength {
+                expected: 20,
+                got: 19
+            }
+        ));
+    }
+
+    #[test]
+    fn hybrid_torrent_has_both_hashes() {
+        let data = hybrid_torrent(b"i20000e");
+        let torrent = Torrent::from_bytes(&data).unwrap();
+
+        assert!(torrent.info.is_hybrid());
+        assert!(torrent.info.files.as_ref().unwrap()[1].is_padding());
+
+        let v2 = torrent.info_hash_v2().unwrap();
+        assert_eq!(
+            torrent.info_hashes(),
+            [InfoHash::V1(torrent.info.info_hash), InfoHash::V2(v2)]
+        );
+        // v1 hash stays the main one for hybrid torrents
+        assert_eq!(torrent.info_hash(), Some(InfoHash::V1(torrent.info.info_hash)));
+        assert_eq!(torrent.to_bytes().unwrap(), data);
+    }
+
+    #[test]
+    fn error_on_hybrid_mismatch() {
+        let data = hybrid_torrent(b"i19999e");
+
+        let err = Torrent::from_bytes(&data).unwrap_err();
+        assert!(matches!(err.kind(), TorrentFileError::HybridMismatch(1)));
+    }
+}
@@ -0,0 +1,250 @@
+#![warn(clippy::all)]
+use std::{borrow::Cow, collections::BTreeMap, io::Write};
+
+use super::{
+    decoder::{DecodeError, Decoder, Token, TokenKind},
+    encoder::{EncodeError, Encoder},
+};
+
+/// A bencode value. Strings are borrowed from the source buffer when
+/// possible, dict keys are kept sorted by their raw bytes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value<'a> {
+    Int(i64),
+    Bytes(Cow<'a, [u8]>),
+    List(Vec<Value<'a>>),
+    Dict(BTreeMap<Cow<'a, [u8]>, Value<'a>>),
+}
+
+/// Parses exactly one bencode value, the whole `src` must be consumed.
+pub fn parse(src: &[u8]) -> Result<Value<'_>, DecodeError> {
+    let mut dec = Decoder::new(src);
+    let token = dec.next_token()?;
+    let value = parse_value(&mut dec, token)?;
+
+    if !dec.is_finished() {
+        return Err(DecodeError::TrailingBytes(dec.pos()));
+    }
+    Ok(value)
+}
+
+fn parse_value<'a>(dec: &mut Decoder<'a>, token: Token<'a>) -> Result<Value<'a>, DecodeError> {
+    match token {
+        Token::Int(i) => Ok(Value::Int(i)),
+        Token::String(s) => Ok(Value::Bytes(s)),
+        Token::EndObject(pos) => Err(DecodeError::UnexpectedObjectClosure(pos)),
+
+        Token::BeginList(_) => {
+            let mut list = Vec::new();
+            loop {
+                match dec.next_token()? {
+                    Token::EndObject(_) => return Ok(Value::List(list)),
+                    token => list.push(parse_value(dec, token)?),
+                }
+            }
+        }
+
+        Token::BeginDict(_) => {
+            let mut dict = BTreeMap::new();
+            loop {
+                let key_pos = dec.pos();
+                let key = match dec.next_token()? {
+                    Token::EndObject(_) => return Ok(Value::Dict(dict)),
+                    Token::String(key) => key,
+                    _ => return Err(DecodeError::KeyNotString(key_pos)),
+                };
+
+                let token = dec.next_token()?;
+                let value = parse_value(dec, token)?;
+                if dict.insert(key, value).is_some() {
+                    return Err(DecodeError::DuplicateKey(key_pos));
+                }
+            }
+        }
+    }
+}
+
+impl<'a> Value<'a> {
+    /// Looks a key up if the value is a dict.
+    pub fn get(&self, key: &[u8]) -> Option<&Value<'a>> {
+        match self {
+            Value::Dict(dict) => dict.get(key),
+            _ => None,
+        }
+    }
+
+    /// Looks an element up if the value is a list.
+    pub fn index(&self, i: usize) -> Option<&Value<'a>> {
+        match self {
+            Value::List(list) => list.get(i),
+            _ => None,
+        }
+    }
+
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Value::Int(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Value::Bytes(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    /// Returns the string if the value is a valid UTF-8 byte string.
+    pub fn as_str(&self) -> Option<&str> {
+        self.as_bytes().and_then(|b| std::str::from_utf8(b).ok())
+    }
+
+    pub fn as_list(&self) -> Option<&[Value<'a>]> {
+        match self {
+            Value::List(list) => Some(list),
+            _ => None,
+        }
+    }
+
+    pub fn as_dict(&self) -> Option<&BTreeMap<Cow<'a, [u8]>, Value<'a>>> {
+        match self {
+            Value::Dict(dict) => Some(dict),
+            _ => None,
+        }
+    }
+
+    pub fn kind(&self) -> TokenKind {
+        match self {
+            Value::Int(_) => TokenKind::Int,
+            Value::Bytes(_) => TokenKind::String,
+            Value::List(_) => TokenKind::BeginList,
+            Value::Dict(_) => TokenKind::BeginDict,
+        }
+    }
+
+    /// Detaches the value from the buffer it was parsed from.
+    pub fn into_owned(self) -> Value<'static> {
+        match self {
+            Value::Int(i) => Value::Int(i),
+            Value::Bytes(b) => Value::Bytes(Cow::Owned(b.into_owned())),
+            Value::List(list) => Value::List(list.into_iter().map(Value::into_owned).collect()),
+            Value::Dict(dict) => Value::Dict(
+                dict.into_iter()
+                    .map(|(k, v)| (Cow::Owned(k.into_owned()), v.into_owned()))
+                    .collect(),
+            ),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, EncodeError> {
+        let mut enc = Encoder::new(Vec::new());
+        self.encode(&mut enc)?;
+        enc.into_inner()
+    }
+
+    pub fn encode<W: Write>(&self, enc: &mut Encoder<W>) -> Result<(), EncodeError> {
+        match self {
+            Value::Int(i) => enc.int(*i),
+            Value::Bytes(b) => enc.bytes(b),
+            Value::List(list) => {
+                enc.begin_list()?;
+                for v in list {
+                    v.encode(enc)?;
+                }
+                enc.end()
+            }
+            Value::Dict(dict) => {
+                enc.begin_dict()?;
+                for (k, v) in dict {
+                    enc.bytes(k)?;
+                    v.encode(enc)?;
+                }
+                enc.end()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_value {
+    use super::*;
+
+    #[test]
+    fn valid_scalars() {
+        assert_eq!(parse(b"i42e").unwrap(), Value::Int(42));
+        assert_eq!(
+            parse(b"4:spam").unwrap(),
+            Value::Bytes(Cow::Borrowed(b"spam"))
+        );
+    }
+
+    #[test]
+    fn nested_lookup() {
+        let v = parse(b"d4:infod4:name4:test6:lengthi3ee8:url-listl1:a1:bee").unwrap();
+
+        assert_eq!(
+            v.get(b"info").unwrap().get(b"name").unwrap().as_str(),
+            Some("test")
+        );
+        assert_eq!(
+            v.get(b"info").unwrap().get(b"length").unwrap().as_int(),
+            Some(3)
+        );
+        assert_eq!(
+            v.get(b"url-list").unwrap().index(1).unwrap().as_str(),
+            Some("b")
+        );
+        assert!(v.get(b"comment").is_none());
+        assert!(v.get(b"info").unwrap().index(0).is_none());
+    }
+
+    #[test]
+    fn strings_are_borrowed() {
+        let v = parse(b"l3:fooe").unwrap();
+
+        assert!(matches!(
+            v.index(0).unwrap(),
+            Value::Bytes(Cow::Borrowed(_))
+        ));
+    }
+
+    #[test]
+    fn reencoding_is_canonical() {
+        let v = parse(b"d1:bi1e1:ali2eee").unwrap();
+
+        assert_eq!(v.to_bytes().unwrap(), b"d1:ali2ee1:bi1ee");
+    }
+
+    #[test]
+    fn error_on_trailing_bytes() {
+        assert!(matches!(
+            parse(b"i1ei2e").unwrap_err(),
+            DecodeError::TrailingBytes(3)
+        ));
+    }
+
+    #[test]
+    fn error_on_non_string_key() {
+        assert!(matches!(
+            parse(b"di1ei2ee").unwrap_err(),
+            DecodeError::KeyNotString(1)
+        ));
+    }
+
+    #[test]
+    fn error_on_duplicate_key() {
+        assert!(matches!(
+            parse(b"d1:ai1e1:ai2ee").unwrap_err(),
+            DecodeError::DuplicateKey(7)
+        ));
+    }
+
+    #[test]
+    fn error_on_unclosed_list() {
+        assert!(matches!(
+            parse(b"li1e").unwrap_err(),
+            DecodeError::PosOutOfBounds
+        ));
+    }
+}
@@ -2,9 +2,15 @@
 #[global_allocator]
 static ALLOC: dhat::Alloc = dhat::Alloc;
 
-use std::{env, fs};
+use std::{
+    env, fs,
+    hint::black_box,
+    time::{Duration, Instant},
+};
 
-use tcore::bencode::torrent::Torrent;
+use tcore::bencode::torrent::{MappedTorrent, Torrent, TorrentRef};
+
+const ROUNDS: u32 = 100;
 
 fn main() {
     #[cfg(feature = "dhat-heap")]
@@ -18,6 +24,31 @@ fn main() {
     let path = &args[1];
 
     let file = fs::read(path).expect("file must be available for reading");
+    let mapped = MappedTorrent::open(path).expect("file must be available for mapping");
+
+    report(
+        "Torrent::from_bytes",
+        bench(|| Torrent::from_bytes(&file).expect("torrent file must be parsed")),
+    );
+    report(
+        "TorrentRef::from_bytes",
+        bench(|| TorrentRef::from_bytes(&file).expect("torrent file must be parsed")),
+    );
+    report(
+        "MappedTorrent::torrent",
+        bench(|| mapped.torrent().expect("torrent file must be parsed")),
+    );
+}
+
+/// Average time of one call over `ROUNDS` calls.
+fn bench<T>(mut f: impl FnMut() -> T) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        black_box(f());
+    }
+    start.elapsed() / ROUNDS
+}
 
-    Torrent::from_bytes(&file).expect("torrent file must be parsed");
+fn report(name: &str, took: Duration) {
+    println!("{name:<24} {took:?} per parse");
 }
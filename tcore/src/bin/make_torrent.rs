@@ -0,0 +1,31 @@
+use std::{env, io::Write};
+
+use tcore::bencode::TorrentCreator;
+
+fn main() -> anyhow::Result<()> {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 3 {
+        panic!("usage: make_torrent <file or dir> <out.torrent> [tracker url]...")
+    }
+
+    let mut creator = TorrentCreator::new(&args[1]);
+    // one tracker per tier, tried in the given order
+    for url in &args[3..] {
+        creator = creator.tier(vec![url.clone()]);
+    }
+
+    let torrent = creator.create_file(&args[2], |hashed, total| {
+        print!("\rhashing: {:.1}%", hashed as f64 / total as f64 * 100.0);
+        let _ = std::io::stdout().flush();
+    })?;
+    println!();
+
+    println!(
+        "{} -> {}, {} pieces of {} bytes",
+        args[1],
+        args[2],
+        torrent.info.pieces.len() / 20,
+        torrent.info.piece_length
+    );
+    Ok(())
+}
@@ -0,0 +1,387 @@
+use std::{collections::HashSet, env, fs, path::Path, process::ExitCode};
+
+use serde::Serialize;
+use tcore::{
+    bencode::{Info, Torrent, TorrentFileError, validate_canonical},
+    ids::InfoHash,
+    magnet::Magnet,
+    sessions::sanitize::{map_paths, sanitize_component},
+};
+
+/// Smallest piece length clients expect, and the smallest BEP 52 allows.
+const MIN_PIECE_LENGTH: u64 = 16 * 1024;
+
+/// Everything worth knowing about a .torrent, printed as text or JSON.
+#[derive(Serialize)]
+struct Report {
+    name: String,
+    info_hash: Option<InfoHash>,
+    info_hash_v2: Option<InfoHash>,
+    total_length: u64,
+    piece_length: u64,
+    piece_count: usize,
+    private: bool,
+    comment: Option<String>,
+    created_by: Option<String>,
+    /// RFC 3339.
+    creation_date: Option<String>,
+    encoding: Option<String>,
+    trackers: Vec<Vec<String>>,
+    web_seeds: Vec<String>,
+    /// Padding files are left out.
+    files: Vec<FileReport>,
+    magnet: String,
+    warnings: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct FileReport {
+    path: String,
+    length: u64,
+    executable: bool,
+    hidden: bool,
+    symlink: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ErrorReport {
+    error: String,
+}
+
+/// Prints what a .torrent holds and lints it. Exits with 1 when the file
+/// can't be read or is not a valid torrent, warnings don't change the exit
+/// code.
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let json = args.iter().any(|arg| arg == "--json");
+    let paths: Vec<&String> = args.iter().filter(|arg| *arg != "--json").collect();
+    let [path] = paths[..] else {
+        eprintln!("usage: torrent-info [--json] <file.torrent>");
+        return ExitCode::from(2);
+    };
+
+    match (inspect(path), json) {
+        (Ok(report), true) => {
+            println!("{}", serde_json::to_string_pretty(&report).unwrap());
+            ExitCode::SUCCESS
+        }
+        (Ok(report), false) => {
+            print_report(&report);
+            ExitCode::SUCCESS
+        }
+        (Err(e), true) => {
+            let error = ErrorReport {
+                error: e.to_string(),
+            };
+            println!("{}", serde_json::to_string_pretty(&error).unwrap());
+            ExitCode::FAILURE
+        }
+        (Err(e), false) => {
+            eprintln!("error: {path}: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn inspect(path: &str) -> Result<Report, TorrentFileError> {
+    // parsing is lenient, the bytes tell whether the encoding was canonical
+    let data = fs::read(path)?;
+    let torrent = Torrent::from_bytes(&data)?;
+    let info = &torrent.info;
+
+    Ok(Report {
+        name: info.name.clone(),
+        info_hash: info.is_v1().then_some(InfoHash::V1(info.info_hash)),
+        info_hash_v2: info.info_hash_v2.map(InfoHash::V2),
+        total_length: info.total_length(),
+        piece_length: info.piece_length,
+        piece_count: info.piece_count(),
+        private: info.private,
+        comment: torrent.comment.clone(),
+        created_by: torrent.created_by.clone(),
+        creation_date: torrent.creation_date.map(|date| date.to_rfc3339()),
+        encoding: torrent.encoding.clone(),
+        trackers: torrent.announce_tiers(),
+        web_seeds: torrent.url_list.clone(),
+        files: files(info),
+        magnet: Magnet::from_torrent(&torrent).to_string(),
+        warnings: lint(&torrent, &data),
+    })
+}
+
+fn files(info: &Info) -> Vec<FileReport> {
+    if let Some(files) = &info.files {
+        return files
+            .iter()
+            .filter(|file| !file.is_padding())
+            .map(|file| FileReport {
+                path: file.path().join("/"),
+                length: file.length(),
+                executable: file.is_executable(),
+                hidden: file.is_hidden(),
+                symlink: file.symlink_path().map(|target| target.join("/")),
+            })
+            .collect();
+    }
+
+    let single = || FileReport {
+        path: info.name.clone(),
+        length: info.total_length(),
+        executable: false,
+        hidden: false,
+        symlink: None,
+    };
+    match &info.file_tree {
+        Some(tree) if info.length.is_none() => tree
+            .iter()
+            .map(|file| FileReport {
+                path: file.path.join("/"),
+                length: file.length,
+                ..single()
+            })
+            .collect(),
+        _ => vec![single()],
+    }
+}
+
+/// Problems that don't stop the torrent from loading, but that other
+/// clients may trip over.
+fn lint(torrent: &Torrent, data: &[u8]) -> Vec<String> {
+    let info = &torrent.info;
+    let mut warnings = Vec::new();
+
+    if !info.piece_length.is_power_of_two() {
+        warnings.push(format!(
+            "piece length of {} bytes is not a power of two",
+            info.piece_length
+        ));
+    }
+    if info.piece_length < MIN_PIECE_LENGTH {
+        warnings.push(format!(
+            "piece length of {} bytes is below {}",
+            info.piece_length,
+            human_size(MIN_PIECE_LENGTH)
+        ));
+    }
+
+    if let Err(e) = validate_canonical(data) {
+        warnings.push(format!(
+            "not canonical bencode, re-encoding changes the bytes: {e}"
+        ));
+    }
+
+    // the same mapping downloads go through
+    let name = sanitize_component(&info.name);
+    if name != info.name {
+        warnings.push(format!("unsafe name: {:?}, saved as {name:?}", info.name));
+    }
+    let paths: Vec<&[String]> = info
+        .files
+        .iter()
+        .flatten()
+        .filter(|file| !file.is_padding())
+        .map(|file| file.path())
+        // hybrid torrents list the same paths twice
+        .chain(
+            info.file_tree
+                .iter()
+                .filter(|_| info.files.is_none())
+                .flatten()
+                .map(|file| &file.path[..]),
+        )
+        .collect();
+    for (path, mapped) in paths.iter().zip(map_paths(paths.iter().copied())) {
+        if !is_unchanged(path, &mapped) {
+            warnings.push(format!("unsafe path: {path:?}, saved as {mapped:?}"));
+        }
+    }
+    for file in info.files.iter().flatten() {
+        if let Some(target) = file.symlink_path()
+            && target.iter().any(|part| part == "..")
+        {
+            warnings.push(format!("symlink leaves the torrent: {:?}", file.path()));
+        }
+    }
+
+    let mut seen = HashSet::new();
+    for url in torrent.announce_tiers().iter().flatten() {
+        if !seen.insert(url.as_str()) {
+            warnings.push(format!("duplicate tracker: {url}"));
+        }
+    }
+
+    warnings
+}
+
+/// Whether the path made it through `map_paths` component by component,
+/// none dropped, cleaned up or renamed.
+fn is_unchanged(path: &[String], mapped: &Path) -> bool {
+    mapped
+        .iter()
+        .map(|part| part.to_str())
+        .eq(path.iter().map(|part| Some(part.as_str())))
+}
+
+fn print_report(report: &Report) {
+    println!("name:          {}", report.name);
+    if let Some(hash) = &report.info_hash {
+        println!("info hash:     {hash}");
+    }
+    if let Some(hash) = &report.info_hash_v2 {
+        println!("info hash v2:  {hash}");
+    }
+    println!(
+        "size:          {} in {} pieces of {}",
+        human_size(report.total_length),
+        report.piece_count,
+        human_size(report.piece_length)
+    );
+    println!("private:       {}", report.private);
+    let optional = [
+        ("comment", &report.comment),
+        ("created by", &report.created_by),
+        ("creation date", &report.creation_date),
+        ("encoding", &report.encoding),
+    ];
+    for (key, value) in optional {
+        if let Some(value) = value {
+            println!("{:<15}{value}", format!("{key}:"));
+        }
+    }
+
+    println!("trackers:");
+    for (i, tier) in report.trackers.iter().enumerate() {
+        println!("  tier {}: {}", i + 1, tier.join(" "));
+    }
+    println!("web seeds:");
+    for url in &report.web_seeds {
+        println!("  {url}");
+    }
+
+    println!("files:");
+    for file in &report.files {
+        let mut flags = String::new();
+        if file.executable {
+            flags.push_str(" [executable]");
+        }
+        if file.hidden {
+            flags.push_str(" [hidden]");
+        }
+        if let Some(target) = &file.symlink {
+            flags.push_str(&format!(" -> {target}"));
+        }
+        println!("  {:>10}  {}{flags}", human_size(file.length), file.path);
+    }
+    println!("magnet:        {}", report.magnet);
+
+    for warning in &report.warnings {
+        eprintln!("warning: {warning}");
+    }
+}
+
+fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    match unit {
+        0 => format!("{bytes} B"),
+        _ => format!("{size:.1} {}", UNITS[unit]),
+    }
+}
+
+#[cfg(test)]
+mod test_torrent_info {
+    use super::*;
+
+    /// A canonical torrent with `info` holding the given keys.
+    fn parse(meta: &str, info: &str) -> (Torrent, Vec<u8>) {
+        let data = format!("d8:announce14:http://tracker{meta}4:infod{info}ee").into_bytes();
+        (Torrent::from_bytes(&data).unwrap(), data)
+    }
+
+    fn single(name: &str, piece_length: u64) -> String {
+        format!(
+            "6:lengthi1e4:name{}:{name}12:piece lengthi{piece_length}e6:pieces20:{}",
+            name.len(),
+            "a".repeat(20)
+        )
+    }
+
+    #[test]
+    fn clean_torrent_has_no_warnings() {
+        let (torrent, data) = parse("", &single("test", 16384));
+        assert!(lint(&torrent, &data).is_empty());
+    }
+
+    #[test]
+    fn odd_piece_lengths() {
+        let (torrent, data) = parse("", &single("test", 20000));
+        assert_eq!(
+            lint(&torrent, &data),
+            ["piece length of 20000 bytes is not a power of two"]
+        );
+
+        let (torrent, data) = parse("", &single("test", 1024));
+        assert_eq!(
+            lint(&torrent, &data),
+            ["piece length of 1024 bytes is below 16.0 KiB"]
+        );
+    }
+
+    #[test]
+    fn non_canonical_input() {
+        // `announce` sorts before `info`
+        let info = single("test", 16384);
+        let data = format!("d4:infod{info}e8:announce14:http://trackere").into_bytes();
+        let torrent = Torrent::from_bytes(&data).unwrap();
+
+        let warnings = lint(&torrent, &data);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("not canonical bencode"));
+    }
+
+    #[test]
+    fn unsafe_paths() {
+        let (torrent, data) = parse("", &single("..", 16384));
+        assert_eq!(
+            lint(&torrent, &data),
+            ["unsafe name: \"..\", saved as \"_\""]
+        );
+
+        let files = "5:filesl\
+            d6:lengthi1e4:pathl2:..6:passwdee\
+            d6:lengthi1e4:pathl3:a\\bee\
+            d6:lengthi1e4:pathl1:cee\
+            d6:lengthi1e4:pathl8:LPT1.txtee\
+            d6:lengthi1e4:pathl4:d\x01 .ee\
+            d6:lengthi1e4:pathl1:Cee\
+            e";
+        let info = format!(
+            "{files}4:name4:test12:piece lengthi16384e6:pieces20:{}",
+            "a".repeat(20)
+        );
+        let (torrent, data) = parse("", &info);
+        assert_eq!(
+            lint(&torrent, &data),
+            [
+                "unsafe path: [\"..\", \"passwd\"], saved as \"_/passwd\"",
+                "unsafe path: [\"a\\\\b\"], saved as \"a_b\"",
+                "unsafe path: [\"LPT1.txt\"], saved as \"_LPT1.txt\"",
+                "unsafe path: [\"d\\u{1} .\"], saved as \"d_\"",
+                "unsafe path: [\"C\"], saved as \"C.1\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn duplicate_trackers() {
+        let tiers = "13:announce-listll14:http://trackerel14:http://trackerel9:http://t2ee";
+        let (torrent, data) = parse(tiers, &single("test", 16384));
+        assert_eq!(lint(&torrent, &data), ["duplicate tracker: http://tracker"]);
+    }
+}
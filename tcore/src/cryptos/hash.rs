@@ -1,4 +1,5 @@
-use sha1::{Sha1, Digest};
+use sha1::{Digest, Sha1};
+use sha2::Sha256;
 
 pub fn make_sha1(src: &[u8]) -> [u8; 20] {
     let mut hasher = Sha1::new();
@@ -16,3 +17,10 @@ pub fn is_equal_sha1_slice(left: &[u8], right: &[u8]) -> bool {
     left[..] == right[..]
 }
 
+pub fn make_sha256(src: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+
+    hasher.update(src);
+
+    hasher.finalize().into()
+}
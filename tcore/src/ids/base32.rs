@@ -0,0 +1,61 @@
+//! RFC 4648 base32 without padding, as used for info hashes in magnet links.
+
+const ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+pub(crate) fn encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(5) * 8);
+    let mut buf = 0u16;
+    let mut bits = 0;
+
+    for b in bytes {
+        buf = (buf << 8) | *b as u16;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(ALPHABET[(buf >> bits) as usize & 31] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(ALPHABET[(buf << (5 - bits)) as usize & 31] as char);
+    }
+    out
+}
+
+/// Case-insensitive. Returns `None` on characters outside of the alphabet.
+pub(crate) fn decode(s: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(s.len() * 5 / 8);
+    let mut buf = 0u16;
+    let mut bits = 0;
+
+    for c in s.bytes() {
+        let v = match c.to_ascii_uppercase() {
+            c @ b'A'..=b'Z' => c - b'A',
+            c @ b'2'..=b'7' => c - b'2' + 26,
+            _ => return None,
+        };
+        buf = (buf << 5) | v as u16;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buf >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod test_base32 {
+    use super::*;
+
+    #[test]
+    fn rfc_4648_vectors() {
+        assert_eq!(encode(b"foobar"), "MZXW6YTBOI");
+        assert_eq!(decode("MZXW6YTBOI").unwrap(), b"foobar");
+        assert_eq!(decode("mzxw6ytb").unwrap(), b"fooba");
+    }
+
+    #[test]
+    fn error_on_unknown_character() {
+        assert!(decode("MZXW6YT1").is_none());
+    }
+}
@@ -0,0 +1,210 @@
+use std::{fmt::Display, marker::PhantomData, str::FromStr};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+use thiserror::Error;
+use url::form_urlencoded;
+
+use super::{base32, hex};
+
+/// What a torrent is known by: the SHA-1 of its info dict, or the SHA-256
+/// of it for v2 torrents (BEP 52). Printed as hex, parsed from hex or base32.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum InfoHash {
+    V1([u8; 20]),
+    V2([u8; 32]),
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ParseIdError {
+    #[error("invalid length of {0}")]
+    InvalidLength(usize),
+    #[error("not hex or base32: {0}")]
+    InvalidCharacter(String),
+}
+
+impl InfoHash {
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            InfoHash::V1(hash) => hash,
+            InfoHash::V2(hash) => hash,
+        }
+    }
+
+    pub fn is_v2(&self) -> bool {
+        matches!(self, InfoHash::V2(_))
+    }
+
+    /// The 20 bytes that go in handshakes and announces: v2 hashes are cut
+    /// to their first 20 bytes.
+    pub fn truncated(&self) -> [u8; 20] {
+        self.as_bytes()[..20].try_into().unwrap()
+    }
+
+    /// The `info_hash` of an announce url.
+    pub fn url_encoded(&self) -> String {
+        form_urlencoded::byte_serialize(&self.truncated()).collect()
+    }
+
+    pub fn to_base32(&self) -> String {
+        base32::encode(self.as_bytes())
+    }
+}
+
+impl From<[u8; 20]> for InfoHash {
+    fn from(hash: [u8; 20]) -> Self {
+        InfoHash::V1(hash)
+    }
+}
+
+impl From<[u8; 32]> for InfoHash {
+    fn from(hash: [u8; 32]) -> Self {
+        InfoHash::V2(hash)
+    }
+}
+
+impl TryFrom<&[u8]> for InfoHash {
+    type Error = ParseIdError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        match bytes.len() {
+            20 => Ok(InfoHash::V1(bytes.try_into().unwrap())),
+            32 => Ok(InfoHash::V2(bytes.try_into().unwrap())),
+            len => Err(ParseIdError::InvalidLength(len)),
+        }
+    }
+}
+
+impl Display for InfoHash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&hex::encode(self.as_bytes()))
+    }
+}
+
+impl FromStr for InfoHash {
+    type Err = ParseIdError;
+
+    /// 40 or 64 hex characters, or 32 or 52 base32 ones.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = match s.len() {
+            40 | 64 => hex::decode(s),
+            32 | 52 => base32::decode(s),
+            len => return Err(ParseIdError::InvalidLength(len)),
+        };
+        let bytes = bytes.ok_or_else(|| ParseIdError::InvalidCharacter(s.to_string()))?;
+        InfoHash::try_from(bytes.as_slice())
+    }
+}
+
+/// Hex in human-readable formats, the bytes themselves otherwise.
+impl Serialize for InfoHash {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match serializer.is_human_readable() {
+            true => serializer.collect_str(self),
+            false => serializer.serialize_bytes(self.as_bytes()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for InfoHash {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_id(deserializer)
+    }
+}
+
+/// Reads an id written by its `Serialize` impl, as text or as bytes.
+pub(super) fn deserialize_id<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: FromStr<Err = ParseIdError> + for<'a> TryFrom<&'a [u8], Error = ParseIdError>,
+    D: Deserializer<'de>,
+{
+    struct IdVisitor<T>(PhantomData<T>);
+
+    impl<T> de::Visitor<'_> for IdVisitor<T>
+    where
+        T: FromStr<Err = ParseIdError> + for<'a> TryFrom<&'a [u8], Error = ParseIdError>,
+    {
+        type Value = T;
+
+        fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            f.write_str("a hash as text or bytes")
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<T, E> {
+            v.parse().map_err(E::custom)
+        }
+
+        fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<T, E> {
+            T::try_from(v).map_err(E::custom)
+        }
+    }
+
+    match deserializer.is_human_readable() {
+        true => deserializer.deserialize_str(IdVisitor(PhantomData)),
+        false => deserializer.deserialize_bytes(IdVisitor(PhantomData)),
+    }
+}
+
+#[cfg(test)]
+mod test_info_hash {
+    use serde::de::{IntoDeserializer, value};
+
+    use crate::bencode;
+
+    use super::*;
+
+    const HEX: &str = "c12fe1c06bba254a9dc9f519b335aa7c1367a88a";
+
+    #[test]
+    fn hex_and_base32() {
+        let hash: InfoHash = HEX.parse().unwrap();
+        assert!(matches!(hash, InfoHash::V1(_)));
+        assert_eq!(hash.to_string(), HEX);
+        assert_eq!(HEX.to_uppercase().parse(), Ok(hash));
+        assert_eq!(hash.to_base32().parse(), Ok(hash));
+
+        let v2 = InfoHash::V2([0xab; 32]);
+        assert_eq!(v2.to_string(), "ab".repeat(32));
+        assert_eq!(v2.to_string().parse(), Ok(v2));
+        assert_eq!(v2.to_base32().len(), 52);
+        assert_eq!(v2.to_base32().parse(), Ok(v2));
+    }
+
+    #[test]
+    fn error_on_malformed_hash() {
+        assert_eq!(
+            "abc".parse::<InfoHash>(),
+            Err(ParseIdError::InvalidLength(3))
+        );
+        assert!(matches!(
+            "x".repeat(40).parse::<InfoHash>(),
+            Err(ParseIdError::InvalidCharacter(_))
+        ));
+        assert_eq!(
+            InfoHash::try_from(&[0; 21][..]),
+            Err(ParseIdError::InvalidLength(21))
+        );
+    }
+
+    #[test]
+    fn url_encoded_is_truncated() {
+        let mut bytes = [b'a'; 32];
+        bytes[1] = b' ';
+        bytes[19] = 0xff;
+        let v2 = InfoHash::V2(bytes);
+
+        assert_eq!(v2.truncated(), bytes[..20]);
+        assert_eq!(v2.url_encoded(), format!("a+{}%FF", "a".repeat(17)));
+    }
+
+    #[test]
+    fn serde_as_text_or_bytes() {
+        let hash: InfoHash = HEX.parse().unwrap();
+
+        let encoded = bencode::to_vec(&hash).unwrap();
+        assert_eq!(encoded, [b"20:", hash.as_bytes()].concat());
+        assert_eq!(bencode::from_bytes::<InfoHash>(&encoded).unwrap(), hash);
+
+        let text: value::StrDeserializer<value::Error> = HEX.into_deserializer();
+        assert_eq!(InfoHash::deserialize(text).unwrap(), hash);
+    }
+}
@@ -0,0 +1,8 @@
+pub(crate) mod base32;
+pub(crate) mod hex;
+mod info_hash;
+mod peer_id;
+
+pub use info_hash::InfoHash;
+pub use info_hash::ParseIdError;
+pub use peer_id::PeerId;
@@ -0,0 +1,101 @@
+use std::{fmt::Display, str::FromStr};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use url::form_urlencoded;
+
+use super::{
+    hex,
+    info_hash::{ParseIdError, deserialize_id},
+};
+
+/// The 20 bytes a client goes by in handshakes and announces. Printed and
+/// parsed as hex.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PeerId([u8; 20]);
+
+impl PeerId {
+    pub fn new(bytes: [u8; 20]) -> PeerId {
+        PeerId(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 20] {
+        &self.0
+    }
+
+    /// The `peer_id` of an announce url.
+    pub fn url_encoded(&self) -> String {
+        form_urlencoded::byte_serialize(&self.0).collect()
+    }
+}
+
+impl From<[u8; 20]> for PeerId {
+    fn from(bytes: [u8; 20]) -> Self {
+        PeerId(bytes)
+    }
+}
+
+impl TryFrom<&[u8]> for PeerId {
+    type Error = ParseIdError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        bytes
+            .try_into()
+            .map(PeerId)
+            .map_err(|_| ParseIdError::InvalidLength(bytes.len()))
+    }
+}
+
+impl Display for PeerId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&hex::encode(&self.0))
+    }
+}
+
+impl FromStr for PeerId {
+    type Err = ParseIdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() != 40 {
+            return Err(ParseIdError::InvalidLength(s.len()));
+        }
+        let bytes = hex::decode(s).ok_or_else(|| ParseIdError::InvalidCharacter(s.to_string()))?;
+        PeerId::try_from(bytes.as_slice())
+    }
+}
+
+/// Hex in human-readable formats, the bytes themselves otherwise.
+impl Serialize for PeerId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match serializer.is_human_readable() {
+            true => serializer.collect_str(self),
+            false => serializer.serialize_bytes(&self.0),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for PeerId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_id(deserializer)
+    }
+}
+
+#[cfg(test)]
+mod test_peer_id {
+    use crate::bencode;
+
+    use super::*;
+
+    #[test]
+    fn formats() {
+        let id = PeerId::new(*b"-TC0010-abcdefghij k");
+
+        assert_eq!(id.to_string(), hex::encode(b"-TC0010-abcdefghij k"));
+        assert_eq!(id.to_string().parse(), Ok(id));
+        assert_eq!(id.url_encoded(), "-TC0010-abcdefghij+k");
+        assert_eq!("ab".parse::<PeerId>(), Err(ParseIdError::InvalidLength(2)));
+
+        let encoded = bencode::to_vec(&id).unwrap();
+        assert_eq!(encoded, b"20:-TC0010-abcdefghij k");
+        assert_eq!(bencode::from_bytes::<PeerId>(&encoded).unwrap(), id);
+    }
+}
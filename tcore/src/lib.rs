@@ -2,3 +2,5 @@
 pub mod bencode;
 pub mod sessions;
 pub mod cryptos;
+pub mod ids;
+pub mod magnet;
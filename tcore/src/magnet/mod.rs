@@ -0,0 +1,4 @@
+mod uri;
+
+pub use uri::Magnet;
+pub use uri::MagnetError;
@@ -0,0 +1,292 @@
+use std::{fmt::Display, ops::RangeInclusive, str::FromStr};
+
+use thiserror::Error;
+use url::form_urlencoded;
+
+use crate::{
+    bencode::Torrent,
+    ids::{InfoHash, base32, hex},
+};
+
+/// Multihash prefix of a SHA-256 digest: code 0x12, length 32.
+const SHA256_MULTIHASH: [u8; 2] = [0x12, 0x20];
+
+/// A magnet link (BEP 9), with the BEP 52 `btmh` extension.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Magnet {
+    /// v1 info hash from `xt=urn:btih:`.
+    pub info_hash: Option<[u8; 20]>,
+    /// v2 info hash from `xt=urn:btmh:`.
+    pub info_hash_v2: Option<[u8; 32]>,
+    /// `dn`, the display name.
+    pub name: Option<String>,
+    /// `tr`, tracker urls.
+    pub trackers: Vec<String>,
+    /// `ws`, web seed urls.
+    pub web_seeds: Vec<String>,
+    /// `x.pe`, peer addresses as `host:port`.
+    pub peers: Vec<String>,
+    /// `so`, indices of the files to download.
+    pub select_only: Vec<RangeInclusive<usize>>,
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum MagnetError {
+    #[error("not a magnet link")]
+    NotMagnet,
+    #[error("magnet link has no btih or btmh exact topic")]
+    MissingInfoHash,
+    #[error("invalid info hash: {0}")]
+    InvalidInfoHash(String),
+    #[error("unsupported multihash: {0}")]
+    UnsupportedMultihash(String),
+    #[error("invalid file selection: {0}")]
+    InvalidSelectOnly(String),
+}
+
+impl Magnet {
+    pub fn parse(uri: &str) -> Result<Magnet, MagnetError> {
+        let query = uri.strip_prefix("magnet:?").ok_or(MagnetError::NotMagnet)?;
+
+        let mut magnet = Magnet::default();
+        for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+            // keys may be numbered to keep several values apart, e.g. `tr.1`
+            let key = match key.rsplit_once('.') {
+                Some((name, n)) if n.bytes().all(|b| b.is_ascii_digit()) => name,
+                _ => &*key,
+            };
+
+            match key {
+                "xt" => magnet.parse_exact_topic(&value)?,
+                "dn" => magnet.name = Some(value.into_owned()),
+                "tr" => magnet.trackers.push(value.into_owned()),
+                "ws" => magnet.web_seeds.push(value.into_owned()),
+                "x.pe" => magnet.peers.push(value.into_owned()),
+                "so" => magnet.select_only = parse_select_only(&value)?,
+                _ => {}
+            }
+        }
+
+        if magnet.info_hash.is_none() && magnet.info_hash_v2.is_none() {
+            return Err(MagnetError::MissingInfoHash);
+        }
+        Ok(magnet)
+    }
+
+    /// Magnet link of a parsed torrent, with its name, trackers, web seeds
+    /// and both hashes of hybrid torrents.
+    pub fn from_torrent(torrent: &Torrent) -> Magnet {
+        Magnet {
+            info_hash: torrent.info.is_v1().then_some(torrent.info.info_hash),
+            info_hash_v2: torrent.info_hash_v2(),
+            name: Some(torrent.info.name.clone()),
+            trackers: torrent.announce_tiers().into_iter().flatten().collect(),
+            web_seeds: torrent.url_list.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// Every hash the torrent is known by, like [`Torrent::info_hashes`].
+    pub fn info_hashes(&self) -> Vec<InfoHash> {
+        let v1 = self.info_hash.map(InfoHash::V1);
+        let v2 = self.info_hash_v2.map(InfoHash::V2);
+        v1.into_iter().chain(v2).collect()
+    }
+
+    fn parse_exact_topic(&mut self, xt: &str) -> Result<(), MagnetError> {
+        let invalid = || MagnetError::InvalidInfoHash(xt.to_string());
+
+        if let Some(hash) = xt.strip_prefix("urn:btih:") {
+            let bytes = match hash.len() {
+                40 => hex::decode(hash),
+                32 => base32::decode(hash),
+                _ => None,
+            };
+            self.info_hash = Some(
+                bytes
+                    .ok_or_else(invalid)?
+                    .try_into()
+                    .map_err(|_| invalid())?,
+            );
+        } else if let Some(hash) = xt.strip_prefix("urn:btmh:") {
+            let bytes = hex::decode(hash).ok_or_else(invalid)?;
+            let digest = match bytes.split_at_checked(2) {
+                Some((prefix, digest)) if prefix == SHA256_MULTIHASH => digest,
+                _ => return Err(MagnetError::UnsupportedMultihash(hash.to_string())),
+            };
+            self.info_hash_v2 = Some(digest.try_into().map_err(|_| invalid())?);
+        }
+        // other topics, e.g. ed2k, are none of our business
+        Ok(())
+    }
+}
+
+impl FromStr for Magnet {
+    type Err = MagnetError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Magnet::parse(s)
+    }
+}
+
+impl Display for Magnet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut query = form_urlencoded::Serializer::new(String::new());
+
+        if let Some(hash) = &self.info_hash {
+            query.append_pair("xt", &format!("urn:btih:{}", hex::encode(hash)));
+        }
+        if let Some(hash) = &self.info_hash_v2 {
+            let multihash = [&SHA256_MULTIHASH[..], hash].concat();
+            query.append_pair("xt", &format!("urn:btmh:{}", hex::encode(&multihash)));
+        }
+        if let Some(name) = &self.name {
+            query.append_pair("dn", name);
+        }
+        for tr in &self.trackers {
+            query.append_pair("tr", tr);
+        }
+        for ws in &self.web_seeds {
+            query.append_pair("ws", ws);
+        }
+        for pe in &self.peers {
+            query.append_pair("x.pe", pe);
+        }
+        if !self.select_only.is_empty() {
+            let so: Vec<String> = self
+                .select_only
+                .iter()
+                .map(|r| match r.start() == r.end() {
+                    true => r.start().to_string(),
+                    false => format!("{}-{}", r.start(), r.end()),
+                })
+                .collect();
+            query.append_pair("so", &so.join(","));
+        }
+
+        // the urn colons stay readable, as everybody writes them
+        write!(f, "magnet:?{}", query.finish().replace("%3A", ":"))
+    }
+}
+
+/// Parses `so` values like `0,2,4-6`.
+fn parse_select_only(so: &str) -> Result<Vec<RangeInclusive<usize>>, MagnetError> {
+    let invalid = || MagnetError::InvalidSelectOnly(so.to_string());
+
+    so.split(',')
+        .map(|part| {
+            let (start, end) = part.split_once('-').unwrap_or((part, part));
+            let start: usize = start.parse().map_err(|_| invalid())?;
+            let end: usize = end.parse().map_err(|_| invalid())?;
+            if start > end {
+                return Err(invalid());
+            }
+            Ok(start..=end)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test_magnet {
+    use super::*;
+
+    const HEX: &str = "c12fe1c06bba254a9dc9f519b335aa7c1367a88a";
+
+    fn hash() -> [u8; 20] {
+        hex::decode(HEX).unwrap().try_into().unwrap()
+    }
+
+    #[test]
+    fn hex_and_base32_btih() {
+        let hex = Magnet::parse(&format!("magnet:?xt=urn:btih:{HEX}")).unwrap();
+        let b32 =
+            Magnet::parse(&format!("magnet:?xt=urn:btih:{}", base32::encode(&hash()))).unwrap();
+
+        assert_eq!(hex.info_hash, Some(hash()));
+        assert_eq!(b32, hex);
+    }
+
+    #[test]
+    fn all_known_params() {
+        let magnet: Magnet = concat!(
+            "magnet:?xt=urn:btih:c12fe1c06bba254a9dc9f519b335aa7c1367a88a",
+            "&xt=urn:btmh:1220caf1e1c30e81cb361b9ee167c4aa64228a7fa4fa9f6105232b28ad099f3a302e",
+            "&dn=Some+File%20Name",
+            "&tr.1=http%3A%2F%2Ftracker.one%2Fannounce",
+            "&tr.2=udp://tracker.two:80",
+            "&ws=http://seed/file",
+            "&x.pe=10.0.0.1:6881",
+            "&so=0,2,4-6",
+            "&unknown=1",
+        )
+        .parse()
+        .unwrap();
+
+        assert_eq!(magnet.info_hash, Some(hash()));
+        assert_eq!(magnet.info_hash_v2.unwrap()[..2], [0xca, 0xf1]);
+        assert_eq!(magnet.name.as_deref(), Some("Some File Name"));
+        assert_eq!(
+            magnet.trackers,
+            ["http://tracker.one/announce", "udp://tracker.two:80"]
+        );
+        assert_eq!(magnet.web_seeds, ["http://seed/file"]);
+        assert_eq!(magnet.peers, ["10.0.0.1:6881"]);
+        assert_eq!(magnet.select_only, [0..=0, 2..=2, 4..=6]);
+    }
+
+    #[test]
+    fn display_parses_back() {
+        let magnet = Magnet {
+            info_hash: Some(hash()),
+            info_hash_v2: Some([7; 32]),
+            name: Some("a & b".to_string()),
+            trackers: vec!["http://t/announce?x=1".to_string()],
+            select_only: vec![1..=1, 3..=5],
+            ..Default::default()
+        };
+        let uri = magnet.to_string();
+
+        assert!(uri.starts_with(&format!("magnet:?xt=urn:btih:{HEX}&xt=urn:btmh:1220")));
+        assert_eq!(Magnet::parse(&uri).unwrap(), magnet);
+    }
+
+    #[test]
+    fn from_torrent() {
+        let data = concat!(
+            "d8:announce14:http://tracker4:infod",
+            "6:lengthi1e4:name4:test12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaa",
+            "ee"
+        );
+        let torrent = Torrent::from_bytes(data.as_bytes()).unwrap();
+        let magnet = Magnet::from_torrent(&torrent);
+
+        assert_eq!(magnet.info_hash.map(InfoHash::V1), torrent.info_hash());
+        assert_eq!(magnet.info_hash_v2, None);
+        assert_eq!(magnet.name.as_deref(), Some("test"));
+        assert_eq!(magnet.trackers, ["http://tracker"]);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            Magnet::parse("http://example.com").unwrap_err(),
+            MagnetError::NotMagnet
+        );
+        assert_eq!(
+            Magnet::parse("magnet:?dn=test").unwrap_err(),
+            MagnetError::MissingInfoHash
+        );
+        assert!(matches!(
+            Magnet::parse("magnet:?xt=urn:btih:1234").unwrap_err(),
+            MagnetError::InvalidInfoHash(_)
+        ));
+        assert!(matches!(
+            Magnet::parse("magnet:?xt=urn:btmh:1114aaaa").unwrap_err(),
+            MagnetError::UnsupportedMultihash(_)
+        ));
+        assert!(matches!(
+            Magnet::parse(&format!("magnet:?xt=urn:btih:{HEX}&so=3-1")).unwrap_err(),
+            MagnetError::InvalidSelectOnly(_)
+        ));
+    }
+}
@@ -0,0 +1,156 @@
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    time::Duration,
+};
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::bencode::{DeserializeError, Value, from_bytes};
+
+/// Used when the tracker does not say how often to come back.
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(30 * 60);
+
+/// What a tracker answered to an announce (BEP 3).
+#[derive(Debug, PartialEq)]
+pub(super) struct AnnounceResponse {
+    pub interval: Duration,
+    pub peers: Vec<SocketAddr>,
+}
+
+#[derive(Error, Debug)]
+pub(super) enum AnnounceError {
+    #[error("request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("invalid response: {0}")]
+    Deserialize(#[from] DeserializeError),
+    #[error("tracker refused: {0}")]
+    Failure(String),
+    #[error("invalid peer list")]
+    InvalidPeers,
+}
+
+#[derive(Deserialize)]
+struct RawResponse<'a> {
+    #[serde(rename = "failure reason")]
+    failure_reason: Option<String>,
+    interval: Option<u64>,
+    /// Either compact (BEP 23) or a list of dicts.
+    #[serde(borrow)]
+    peers: Option<Value<'a>>,
+    /// Compact IPv6 peers (BEP 7).
+    #[serde(borrow)]
+    peers6: Option<&'a [u8]>,
+}
+
+impl AnnounceResponse {
+    pub(super) fn parse(body: &[u8]) -> Result<AnnounceResponse, AnnounceError> {
+        let raw: RawResponse = from_bytes(body)?;
+        if let Some(reason) = raw.failure_reason {
+            return Err(AnnounceError::Failure(reason));
+        }
+
+        let mut peers = match raw.peers {
+            Some(Value::Bytes(compact)) => compact_peers::<4>(&compact)?,
+            Some(Value::List(list)) => list.iter().filter_map(dict_peer).collect(),
+            Some(_) => return Err(AnnounceError::InvalidPeers),
+            None => Vec::new(),
+        };
+        if let Some(compact) = raw.peers6 {
+            peers.extend(compact_peers::<16>(compact)?);
+        }
+
+        Ok(AnnounceResponse {
+            interval: raw.interval.map_or(DEFAULT_INTERVAL, Duration::from_secs),
+            peers,
+        })
+    }
+}
+
+/// Addresses packed as `N` bytes of ip and 2 bytes of port.
+fn compact_peers<const N: usize>(bytes: &[u8]) -> Result<Vec<SocketAddr>, AnnounceError> {
+    if !bytes.len().is_multiple_of(N + 2) {
+        return Err(AnnounceError::InvalidPeers);
+    }
+
+    let peers = bytes
+        .chunks_exact(N + 2)
+        .map(|chunk| {
+            let ip: IpAddr = match N {
+                4 => Ipv4Addr::from(<[u8; 4]>::try_from(&chunk[..4]).unwrap()).into(),
+                _ => Ipv6Addr::from(<[u8; 16]>::try_from(&chunk[..16]).unwrap()).into(),
+            };
+            SocketAddr::new(ip, u16::from_be_bytes([chunk[N], chunk[N + 1]]))
+        })
+        .collect();
+    Ok(peers)
+}
+
+/// Peers with a hostname instead of an ip are skipped.
+fn dict_peer(peer: &Value) -> Option<SocketAddr> {
+    let ip = match peer.get(b"ip")? {
+        Value::Bytes(ip) => std::str::from_utf8(ip).ok()?.parse().ok()?,
+        _ => return None,
+    };
+    let port = match peer.get(b"port")? {
+        Value::Int(port) => u16::try_from(*port).ok()?,
+        _ => return None,
+    };
+    Some(SocketAddr::new(ip, port))
+}
+
+#[cfg(test)]
+mod test_announce {
+    use super::*;
+
+    #[test]
+    fn compact_peers() {
+        let body = [
+            b"d8:intervali900e5:peers12:".as_slice(),
+            &[127, 0, 0, 1, 0x1a, 0xe1, 10, 0, 0, 2, 0, 80],
+            b"6:peers618:",
+            &[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 81],
+            b"e",
+        ]
+        .concat();
+
+        let response = AnnounceResponse::parse(&body).unwrap();
+        assert_eq!(response.interval, Duration::from_secs(900));
+        assert_eq!(
+            response.peers,
+            [
+                "127.0.0.1:6881".parse().unwrap(),
+                "10.0.0.2:80".parse().unwrap(),
+                "[::1]:81".parse().unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn dict_peers() {
+        let body = concat!(
+            "d5:peersl",
+            "d2:ip9:127.0.0.17:peer id20:aaaaaaaaaaaaaaaaaaaa4:porti6881ee",
+            "d2:ip11:example.com4:porti80ee",
+            "ee"
+        );
+
+        let response = AnnounceResponse::parse(body.as_bytes()).unwrap();
+        assert_eq!(response.interval, DEFAULT_INTERVAL);
+        assert_eq!(response.peers, ["127.0.0.1:6881".parse().unwrap()]);
+    }
+
+    #[test]
+    fn error_on_failure_reason() {
+        let body = b"d14:failure reason12:unregisterede";
+
+        assert!(matches!(
+            AnnounceResponse::parse(body),
+            Err(AnnounceError::Failure(reason)) if reason == "unregistered"
+        ));
+        assert!(matches!(
+            AnnounceResponse::parse(b"d5:peers5:abcdee"),
+            Err(AnnounceError::InvalidPeers)
+        ));
+    }
+}
@@ -0,0 +1,84 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Extended message id of the extension handshake (BEP 10).
+pub(super) const HANDSHAKE_ID: u8 = 0;
+
+/// Id peers are asked to use for the `ut_metadata` messages they send us.
+pub(super) const UT_METADATA_ID: u8 = 1;
+
+pub(super) const UT_METADATA: &str = "ut_metadata";
+
+/// Metadata goes over the wire in pieces of this size, only the last one
+/// is shorter (BEP 9).
+pub(super) const METADATA_PIECE_LEN: usize = 16 * 1024;
+
+/// Payload of the extension handshake (BEP 10).
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+pub(super) struct ExtendedHandshake {
+    /// Extension names mapped to the message ids the sender wants them
+    /// under, 0 disables one.
+    #[serde(default)]
+    pub m: BTreeMap<String, u8>,
+    /// Size of the info dict, from peers that have it (BEP 9).
+    pub metadata_size: Option<usize>,
+    /// Client name and version.
+    pub v: Option<String>,
+}
+
+impl ExtendedHandshake {
+    /// Id the peer wants messages of the extension under, if it speaks it.
+    pub(super) fn id_of(&self, name: &str) -> Option<u8> {
+        self.m.get(name).copied().filter(|&id| id != 0)
+    }
+}
+
+pub(super) const METADATA_REQUEST: u8 = 0;
+pub(super) const METADATA_DATA: u8 = 1;
+pub(super) const METADATA_REJECT: u8 = 2;
+
+/// Dict heading every `ut_metadata` message, `data` messages are
+/// followed by the piece itself (BEP 9).
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub(super) struct MetadataMsg {
+    pub msg_type: u8,
+    pub piece: u32,
+    pub total_size: Option<usize>,
+}
+
+#[cfg(test)]
+mod test_extension {
+    use super::*;
+    use crate::bencode::{from_bytes, from_prefix, to_vec};
+
+    #[test]
+    fn handshake_round_trip() {
+        let handshake = ExtendedHandshake {
+            m: BTreeMap::from([(UT_METADATA.to_string(), 3), ("ut_pex".to_string(), 0)]),
+            metadata_size: Some(31235),
+            v: None,
+        };
+
+        let bytes = to_vec(&handshake).unwrap();
+        assert_eq!(
+            bytes,
+            b"d1:md11:ut_metadatai3e6:ut_pexi0ee13:metadata_sizei31235ee"
+        );
+
+        let parsed: ExtendedHandshake = from_bytes(&bytes).unwrap();
+        assert_eq!(parsed.id_of(UT_METADATA), Some(3));
+        assert_eq!(parsed.id_of("ut_pex"), None);
+        assert_eq!(parsed, handshake);
+    }
+
+    #[test]
+    fn data_message_is_followed_by_piece() {
+        let payload = b"d8:msg_typei1e5:piecei0e10:total_sizei4eexxxx";
+
+        let (msg, len): (MetadataMsg, _) = from_prefix(payload).unwrap();
+        assert_eq!(msg.msg_type, METADATA_DATA);
+        assert_eq!(msg.total_size, Some(4));
+        assert_eq!(&payload[len..], b"xxxx");
+    }
+}
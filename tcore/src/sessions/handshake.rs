@@ -0,0 +1,105 @@
+use thiserror::Error;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+pub(super) const PROTOCOL: &[u8; 19] = b"BitTorrent protocol";
+
+/// Length of the handshake up to and including the info hash, enough
+/// to tell which torrent the peer wants.
+pub(super) const HEAD_LEN: usize = 1 + PROTOCOL.len() + 8 + 20;
+
+/// Length of the whole handshake, with the peer id.
+pub(super) const HANDSHAKE_LEN: usize = HEAD_LEN + 20;
+
+/// Reserved bit of the extension protocol (BEP 10), in the 6th byte.
+const EXTENSION_BIT: u8 = 0x10;
+
+/// First part of the BitTorrent handshake (BEP 3) sent by a connecting peer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) struct HandshakeHead {
+    pub reserved: [u8; 8],
+    pub info_hash: [u8; 20],
+}
+
+#[derive(Error, Debug)]
+pub(super) enum HandshakeError {
+    #[error("i/o error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("peer speaks an unknown protocol")]
+    UnknownProtocol,
+}
+
+impl HandshakeHead {
+    pub(super) fn parse(buf: &[u8; HEAD_LEN]) -> Result<HandshakeHead, HandshakeError> {
+        if buf[0] as usize != PROTOCOL.len() || &buf[1..20] != PROTOCOL {
+            return Err(HandshakeError::UnknownProtocol);
+        }
+
+        Ok(HandshakeHead {
+            reserved: buf[20..28].try_into().unwrap(),
+            info_hash: buf[28..48].try_into().unwrap(),
+        })
+    }
+
+    pub(super) async fn read<R: AsyncRead + Unpin>(
+        stream: &mut R,
+    ) -> Result<HandshakeHead, HandshakeError> {
+        let mut buf = [0; HEAD_LEN];
+        stream.read_exact(&mut buf).await?;
+        HandshakeHead::parse(&buf)
+    }
+
+    pub(super) fn supports_extensions(&self) -> bool {
+        self.reserved[5] & EXTENSION_BIT != 0
+    }
+}
+
+/// Our handshake, announcing support for the extension protocol.
+pub(super) fn handshake(info_hash: [u8; 20], peer_id: [u8; 20]) -> [u8; HANDSHAKE_LEN] {
+    let mut buf = [0; HANDSHAKE_LEN];
+    buf[0] = PROTOCOL.len() as u8;
+    buf[1..20].copy_from_slice(PROTOCOL);
+    buf[25] = EXTENSION_BIT;
+    buf[28..48].copy_from_slice(&info_hash);
+    buf[48..].copy_from_slice(&peer_id);
+    buf
+}
+
+#[cfg(test)]
+mod test_handshake {
+    use super::*;
+
+    #[test]
+    fn parse_head() {
+        let mut bytes = [0; HEAD_LEN];
+        bytes[0] = 19;
+        bytes[1..20].copy_from_slice(PROTOCOL);
+        bytes[25] = 0x10;
+        bytes[28..].copy_from_slice(&[1; 20]);
+
+        let head = HandshakeHead::parse(&bytes).unwrap();
+        assert_eq!(head.reserved, [0, 0, 0, 0, 0, 0x10, 0, 0]);
+        assert_eq!(head.info_hash, [1; 20]);
+    }
+
+    #[test]
+    fn own_handshake_parses_back() {
+        let bytes = handshake([1; 20], [2; 20]);
+
+        let head = HandshakeHead::parse(bytes[..HEAD_LEN].try_into().unwrap()).unwrap();
+        assert!(head.supports_extensions());
+        assert_eq!(head.info_hash, [1; 20]);
+        assert_eq!(bytes[HEAD_LEN..], [2; 20]);
+    }
+
+    #[test]
+    fn error_on_unknown_protocol() {
+        let mut bytes = [0; HEAD_LEN];
+        bytes[0] = 19;
+        bytes[1..20].copy_from_slice(b"BitTorrent protocoX");
+
+        assert!(matches!(
+            HandshakeHead::parse(&bytes).unwrap_err(),
+            HandshakeError::UnknownProtocol
+        ));
+    }
+}
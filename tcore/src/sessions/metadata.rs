@@ -0,0 +1,480 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use thiserror::Error;
+use tokio::time;
+
+use crate::{
+    bencode::{DeserializeError, Info, TorrentFileError, from_bytes, from_prefix, to_vec},
+    cryptos::hash::{make_sha1, make_sha256},
+    magnet::Magnet,
+    sessions::{
+        extension::{
+            ExtendedHandshake, HANDSHAKE_ID, METADATA_DATA, METADATA_PIECE_LEN, METADATA_REJECT,
+            METADATA_REQUEST, MetadataMsg, UT_METADATA, UT_METADATA_ID,
+        },
+        peer::{Message, PeerConn, PeerError},
+    },
+};
+
+/// Larger info dicts are not worth fetching from strangers.
+const MAX_METADATA_SIZE: usize = 16 * 1024 * 1024;
+
+/// Peers send keep-alives every two minutes, silent ones are gone.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(150);
+
+/// Info dict of a magnet link, assembled from the pieces peers send us.
+#[derive(Clone)]
+pub(super) struct MetadataFetch {
+    info_hash: Option<[u8; 20]>,
+    info_hash_v2: Option<[u8; 32]>,
+    size: usize,
+    pieces: Vec<Option<Vec<u8>>>,
+}
+
+#[derive(Error, Debug)]
+pub(super) enum MetadataError {
+    #[error("peer error: {0}")]
+    Peer(#[from] PeerError),
+    #[error("peer does not share metadata")]
+    Unsupported,
+    #[error("invalid extension message: {0}")]
+    Message(#[from] DeserializeError),
+    #[error("metadata size of {0} is invalid")]
+    InvalidSize(usize),
+    #[error("metadata piece {0} is invalid")]
+    InvalidPiece(u32),
+    #[error("peer rejected metadata piece {0}")]
+    Rejected(u32),
+    #[error("metadata does not match the info hash")]
+    HashMismatch,
+    #[error("invalid metadata: {0}")]
+    Parse(#[from] TorrentFileError),
+}
+
+impl MetadataFetch {
+    pub(super) fn new(magnet: &Magnet) -> MetadataFetch {
+        MetadataFetch {
+            info_hash: magnet.info_hash,
+            info_hash_v2: magnet.info_hash_v2,
+            size: 0,
+            pieces: Vec::new(),
+        }
+    }
+
+    /// The hash to ask peers for, the v1 one when there are both.
+    pub(super) fn info_hash(&self) -> [u8; 20] {
+        self.info_hash
+            .or_else(|| self.info_hash_v2.map(|v2| v2[..20].try_into().unwrap()))
+            .expect("a magnet has at least one info hash")
+    }
+
+    /// Pieces already fetched are kept, unless another peer tells a
+    /// different size.
+    fn set_size(&mut self, size: usize) -> Result<(), MetadataError> {
+        if size == 0 || size > MAX_METADATA_SIZE {
+            return Err(MetadataError::InvalidSize(size));
+        }
+        if size != self.size {
+            self.size = size;
+            self.pieces = vec![None; size.div_ceil(METADATA_PIECE_LEN)];
+        }
+        Ok(())
+    }
+
+    fn missing(&self) -> Vec<u32> {
+        (0..self.pieces.len() as u32)
+            .filter(|&i| self.pieces[i as usize].is_none())
+            .collect()
+    }
+
+    fn add_piece(&mut self, piece: u32, data: &[u8]) -> Result<(), MetadataError> {
+        let i = piece as usize;
+        let expected = match self.pieces.len() {
+            n if i + 1 < n => METADATA_PIECE_LEN,
+            n if i + 1 == n => self.size - i * METADATA_PIECE_LEN,
+            _ => return Err(MetadataError::InvalidPiece(piece)),
+        };
+        if data.len() != expected {
+            return Err(MetadataError::InvalidPiece(piece));
+        }
+
+        self.pieces[i] = Some(data.to_vec());
+        Ok(())
+    }
+
+    fn is_complete(&self) -> bool {
+        !self.pieces.is_empty() && self.pieces.iter().all(Option::is_some)
+    }
+
+    /// Checks the assembled metadata against the magnet's hashes and
+    /// parses it. A bad piece can't be told apart, so all are dropped
+    /// on a mismatch.
+    fn finish(&mut self) -> Result<Info, MetadataError> {
+        let raw: Vec<u8> = self.pieces.iter().flatten().flatten().copied().collect();
+
+        let v1_ok = self.info_hash.is_none_or(|hash| make_sha1(&raw) == hash);
+        let v2_ok = self
+            .info_hash_v2
+            .is_none_or(|hash| make_sha256(&raw) == hash);
+        if !v1_ok || !v2_ok {
+            self.pieces.fill(None);
+            return Err(MetadataError::HashMismatch);
+        }
+
+        Ok(Info::from_bytes(&raw)?)
+    }
+}
+
+/// Fetches the missing metadata pieces from a connected peer over
+/// `ut_metadata` (BEP 9).
+pub(super) async fn fetch_from(
+    peer: &mut PeerConn,
+    fetch: &mut MetadataFetch,
+) -> Result<Info, MetadataError> {
+    if !peer.head.supports_extensions() {
+        return Err(MetadataError::Unsupported);
+    }
+
+    peer.send(&extended_handshake(None)).await?;
+
+    loop {
+        let Message::Extended { id, payload } = peer.recv().await? else {
+            continue;
+        };
+
+        match id {
+            HANDSHAKE_ID => {
+                let theirs: ExtendedHandshake = from_bytes(&payload)?;
+                let ut_metadata = theirs
+                    .id_of(UT_METADATA)
+                    .ok_or(MetadataError::Unsupported)?;
+                fetch.set_size(theirs.metadata_size.ok_or(MetadataError::Unsupported)?)?;
+
+                for piece in fetch.missing() {
+                    let request = MetadataMsg {
+                        msg_type: METADATA_REQUEST,
+                        piece,
+                        total_size: None,
+                    };
+                    peer.send(&Message::Extended {
+                        id: ut_metadata,
+                        payload: to_vec(&request).expect("request always serializes"),
+                    })
+                    .await?;
+                }
+            }
+
+            UT_METADATA_ID => {
+                let (msg, len): (MetadataMsg, _) = from_prefix(&payload)?;
+                match msg.msg_type {
+                    METADATA_DATA => {
+                        fetch.add_piece(msg.piece, &payload[len..])?;
+                        if fetch.is_complete() {
+                            return fetch.finish();
+                        }
+                    }
+                    METADATA_REJECT => return Err(MetadataError::Rejected(msg.piece)),
+                    // we have nothing to share yet
+                    _ => {}
+                }
+            }
+
+            _ => {}
+        }
+    }
+}
+
+/// Answers the `ut_metadata` requests of a peer with slices of the raw
+/// info dict, until the peer leaves or goes silent.
+pub(super) async fn serve_to(peer: &mut PeerConn, raw: &[u8]) -> Result<(), MetadataError> {
+    if !peer.head.supports_extensions() {
+        return Err(MetadataError::Unsupported);
+    }
+    peer.send(&extended_handshake(Some(raw.len()).filter(|&len| len > 0)))
+        .await?;
+
+    let mut ut_metadata = None;
+    loop {
+        let msg = time::timeout(IDLE_TIMEOUT, peer.recv())
+            .await
+            .map_err(|_| PeerError::Timeout)??;
+        let Message::Extended { id, payload } = msg else {
+            continue;
+        };
+
+        match id {
+            HANDSHAKE_ID => {
+                let theirs: ExtendedHandshake = from_bytes(&payload)?;
+                ut_metadata = theirs.id_of(UT_METADATA);
+            }
+
+            UT_METADATA_ID => {
+                let (msg, _): (MetadataMsg, _) = from_prefix(&payload)?;
+                // nowhere to send the answer to without their id:
+                let (METADATA_REQUEST, Some(id)) = (msg.msg_type, ut_metadata) else {
+                    continue;
+                };
+
+                let begin = msg.piece as usize * METADATA_PIECE_LEN;
+                let payload = match raw.get(begin..raw.len().min(begin + METADATA_PIECE_LEN)) {
+                    Some(piece) if !piece.is_empty() => {
+                        let data = MetadataMsg {
+                            msg_type: METADATA_DATA,
+                            piece: msg.piece,
+                            total_size: Some(raw.len()),
+                        };
+                        [&to_vec(&data).expect("data always serializes"), piece].concat()
+                    }
+                    _ => {
+                        let reject = MetadataMsg {
+                            msg_type: METADATA_REJECT,
+                            piece: msg.piece,
+                            total_size: None,
+                        };
+                        to_vec(&reject).expect("reject always serializes")
+                    }
+                };
+                peer.send(&Message::Extended { id, payload }).await?;
+            }
+
+            _ => {}
+        }
+    }
+}
+
+/// Our extension handshake, with the size of the metadata once we have it.
+fn extended_handshake(metadata_size: Option<usize>) -> Message {
+    let ours = ExtendedHandshake {
+        m: BTreeMap::from([(UT_METADATA.to_string(), UT_METADATA_ID)]),
+        metadata_size,
+        ..Default::default()
+    };
+    Message::Extended {
+        id: HANDSHAKE_ID,
+        payload: to_vec(&ours).expect("handshake always serializes"),
+    }
+}
+
+#[cfg(test)]
+mod test_metadata {
+    use std::{net::SocketAddr, time::Duration};
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+        time,
+    };
+
+    use super::*;
+    use crate::sessions::{
+        handshake::{HandshakeHead, handshake},
+        session::{InboundPeer, Session},
+    };
+
+    /// An info dict spanning two metadata pieces.
+    fn info_dict() -> Vec<u8> {
+        [
+            b"d6:lengthi16384000e4:name4:test12:piece lengthi16384e6:pieces20000:".as_slice(),
+            &[7; 20000],
+            b"e",
+        ]
+        .concat()
+    }
+
+    fn magnet(info: &[u8]) -> Magnet {
+        Magnet {
+            info_hash: Some(make_sha1(info)),
+            ..Default::default()
+        }
+    }
+
+    /// A peer that only serves `info` over `ut_metadata`.
+    async fn metadata_seeder(info: Vec<u8>) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut theirs = [0; 68];
+                stream.read_exact(&mut theirs).await.unwrap();
+                stream
+                    .write_all(&handshake(make_sha1(&info), [9; 20]))
+                    .await
+                    .unwrap();
+
+                let ours = ExtendedHandshake {
+                    m: BTreeMap::from([(UT_METADATA.to_string(), 2)]),
+                    metadata_size: Some(info.len()),
+                    v: None,
+                };
+                let msg = Message::Extended {
+                    id: HANDSHAKE_ID,
+                    payload: to_vec(&ours).unwrap(),
+                };
+                msg.write(&mut stream).await.unwrap();
+
+                while let Ok(msg) = Message::read(&mut stream).await {
+                    let Message::Extended { id: 2, payload } = msg else {
+                        continue;
+                    };
+                    let request: MetadataMsg = from_bytes(&payload).unwrap();
+                    let begin = request.piece as usize * METADATA_PIECE_LEN;
+                    let end = info.len().min(begin + METADATA_PIECE_LEN);
+
+                    let data = MetadataMsg {
+                        msg_type: METADATA_DATA,
+                        piece: request.piece,
+                        total_size: Some(info.len()),
+                    };
+                    let payload = [&to_vec(&data).unwrap(), &info[begin..end]].concat();
+                    let msg = Message::Extended {
+                        id: UT_METADATA_ID,
+                        payload,
+                    };
+                    msg.write(&mut stream).await.unwrap();
+                }
+            }
+        });
+        addr
+    }
+
+    #[test]
+    fn pieces_are_assembled_and_checked() {
+        let info = info_dict();
+        let mut fetch = MetadataFetch::new(&magnet(&info));
+        fetch.set_size(info.len()).unwrap();
+        assert_eq!(fetch.missing(), [0, 1]);
+
+        assert!(matches!(
+            fetch.add_piece(1, &info[..10]),
+            Err(MetadataError::InvalidPiece(1))
+        ));
+        fetch.add_piece(1, &info[METADATA_PIECE_LEN..]).unwrap();
+        fetch.add_piece(0, &info[..METADATA_PIECE_LEN]).unwrap();
+        assert!(fetch.is_complete());
+
+        let parsed = fetch.finish().unwrap();
+        assert_eq!(parsed.name, "test");
+        assert_eq!(parsed.info_hash, make_sha1(&info));
+    }
+
+    #[test]
+    fn mismatch_drops_every_piece() {
+        let info = info_dict();
+        let mut fetch = MetadataFetch::new(&magnet(b"another info dict"));
+        fetch.set_size(info.len()).unwrap();
+        fetch.add_piece(0, &info[..METADATA_PIECE_LEN]).unwrap();
+        fetch.add_piece(1, &info[METADATA_PIECE_LEN..]).unwrap();
+
+        assert!(matches!(fetch.finish(), Err(MetadataError::HashMismatch)));
+        assert_eq!(fetch.missing(), [0, 1]);
+        assert!(matches!(
+            fetch.set_size(MAX_METADATA_SIZE + 1),
+            Err(MetadataError::InvalidSize(_))
+        ));
+    }
+
+    /// Accepts one peer and serves `raw` to it with [`serve_to`].
+    async fn responder(raw: Vec<u8>) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            let (mut stream, addr) = listener.accept().await.unwrap();
+            let head = HandshakeHead::read(&mut stream).await.unwrap();
+            let inbound = InboundPeer { stream, addr, head };
+
+            let mut peer = PeerConn::accept(inbound, [9; 20]).await.unwrap();
+            let _ = serve_to(&mut peer, &raw).await;
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn responder_serves_fetcher() {
+        let info = info_dict();
+        let addr = responder(info.clone()).await;
+
+        let mut fetch = MetadataFetch::new(&magnet(&info));
+        let mut peer = PeerConn::connect(addr, fetch.info_hash(), [1; 20])
+            .await
+            .unwrap();
+        let parsed = fetch_from(&mut peer, &mut fetch).await.unwrap();
+
+        assert_eq!(parsed.raw, info);
+    }
+
+    #[tokio::test]
+    async fn responder_rejects_unknown_piece() {
+        let info = info_dict();
+        let addr = responder(info.clone()).await;
+
+        let mut peer = PeerConn::connect(addr, make_sha1(&info), [1; 20])
+            .await
+            .unwrap();
+        peer.send(&extended_handshake(None)).await.unwrap();
+
+        let Message::Extended {
+            id: HANDSHAKE_ID,
+            payload,
+        } = peer.recv().await.unwrap()
+        else {
+            panic!("expected the extension handshake");
+        };
+        let theirs: ExtendedHandshake = from_bytes(&payload).unwrap();
+        assert_eq!(theirs.metadata_size, Some(info.len()));
+
+        let request = MetadataMsg {
+            msg_type: METADATA_REQUEST,
+            piece: 2,
+            total_size: None,
+        };
+        let msg = Message::Extended {
+            id: theirs.id_of(UT_METADATA).unwrap(),
+            payload: to_vec(&request).unwrap(),
+        };
+        peer.send(&msg).await.unwrap();
+
+        let Message::Extended { payload, .. } = peer.recv().await.unwrap() else {
+            panic!("expected an answer");
+        };
+        let reply: MetadataMsg = from_bytes(&payload).unwrap();
+        assert_eq!(reply.msg_type, METADATA_REJECT);
+        assert_eq!(reply.piece, 2);
+    }
+
+    #[tokio::test]
+    async fn fetch_from_peer() {
+        let info = info_dict();
+        let addr = metadata_seeder(info.clone()).await;
+
+        let mut fetch = MetadataFetch::new(&magnet(&info));
+        let mut peer = PeerConn::connect(addr, fetch.info_hash(), [1; 20])
+            .await
+            .unwrap();
+        let parsed = fetch_from(&mut peer, &mut fetch).await.unwrap();
+
+        assert_eq!(parsed, Info::from_bytes(&info).unwrap());
+    }
+
+    #[tokio::test]
+    async fn magnet_leaves_fetching_state() {
+        let info = info_dict();
+        let addr = metadata_seeder(info.clone()).await;
+
+        let mut magnet = magnet(&info);
+        magnet.peers = vec![addr.to_string()];
+
+        let session = Session::bind().await.unwrap();
+        let tracker = session.add_magnet(magnet).begin().await.unwrap();
+        assert!(tracker.status().fetching_metadata);
+
+        time::timeout(Duration::from_secs(5), async {
+            while tracker.status().fetching_metadata {
+                time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .expect("metadata is fetched");
+    }
+}
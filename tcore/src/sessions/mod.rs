@@ -1,3 +1,12 @@
+pub mod sanitize;
 pub mod session;
 pub mod tracker;
+mod announce;
+mod extension;
+mod handshake;
+mod metadata;
+mod peer;
+mod storage;
+mod tiers;
+mod webseed;
 mod worker;
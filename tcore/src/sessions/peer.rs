@@ -0,0 +1,322 @@
+use std::{net::SocketAddr, time::Duration};
+
+use thiserror::Error;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::TcpStream,
+    time,
+};
+
+use crate::sessions::{
+    handshake::{HandshakeError, HandshakeHead, handshake},
+    session::InboundPeer,
+};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Bitfields of the largest torrents fit, anything longer is garbage.
+const MAX_MESSAGE_LEN: usize = 2 * 1024 * 1024;
+
+/// Messages of the peer wire protocol (BEP 3) plus the extension
+/// protocol (BEP 10). A zero length prefix is a keep-alive.
+#[derive(Debug, PartialEq)]
+pub(super) enum Message {
+    KeepAlive,
+    Choke,
+    Unchoke,
+    Interested,
+    NotInterested,
+    Have(u32),
+    Bitfield(Vec<u8>),
+    Request {
+        index: u32,
+        begin: u32,
+        length: u32,
+    },
+    Piece {
+        index: u32,
+        begin: u32,
+        block: Vec<u8>,
+    },
+    Cancel {
+        index: u32,
+        begin: u32,
+        length: u32,
+    },
+    Extended {
+        id: u8,
+        payload: Vec<u8>,
+    },
+    /// Messages of extensions we don't speak, e.g. `port` (BEP 5).
+    Unknown(u8),
+}
+
+#[derive(Error, Debug)]
+pub(super) enum PeerError {
+    #[error("i/o error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("handshake failed: {0}")]
+    Handshake(#[from] HandshakeError),
+    #[error("peer did not answer in time")]
+    Timeout,
+    #[error("peer serves another torrent")]
+    InfoHashMismatch,
+    #[error("message of length {0} is too long")]
+    MessageTooLong(usize),
+    #[error("message {0} is malformed")]
+    InvalidMessage(u8),
+}
+
+impl Message {
+    pub(super) async fn read<R: AsyncRead + Unpin>(r: &mut R) -> Result<Message, PeerError> {
+        let len = r.read_u32().await? as usize;
+        if len == 0 {
+            return Ok(Message::KeepAlive);
+        }
+        if len > MAX_MESSAGE_LEN {
+            return Err(PeerError::MessageTooLong(len));
+        }
+
+        let mut buf = vec![0; len];
+        r.read_exact(&mut buf).await?;
+        Message::parse(buf)
+    }
+
+    fn parse(mut buf: Vec<u8>) -> Result<Message, PeerError> {
+        let id = buf[0];
+        let invalid = || PeerError::InvalidMessage(id);
+        let int = |buf: &[u8], at: usize| -> Result<u32, PeerError> {
+            let bytes = buf.get(at..at + 4).ok_or_else(invalid)?;
+            Ok(u32::from_be_bytes(bytes.try_into().unwrap()))
+        };
+        let exact = |len: usize| match buf.len() == len {
+            true => Ok(()),
+            false => Err(invalid()),
+        };
+
+        let msg = match id {
+            0 => exact(1).map(|_| Message::Choke)?,
+            1 => exact(1).map(|_| Message::Unchoke)?,
+            2 => exact(1).map(|_| Message::Interested)?,
+            3 => exact(1).map(|_| Message::NotInterested)?,
+            4 => {
+                exact(5)?;
+                Message::Have(int(&buf, 1)?)
+            }
+            5 => Message::Bitfield(buf.split_off(1)),
+            6 | 8 => {
+                exact(13)?;
+                let (index, begin, length) = (int(&buf, 1)?, int(&buf, 5)?, int(&buf, 9)?);
+                match id {
+                    6 => Message::Request {
+                        index,
+                        begin,
+                        length,
+                    },
+                    _ => Message::Cancel {
+                        index,
+                        begin,
+                        length,
+                    },
+                }
+            }
+            7 => Message::Piece {
+                index: int(&buf, 1)?,
+                begin: int(&buf, 5)?,
+                block: buf.split_off(9),
+            },
+            20 => Message::Extended {
+                id: *buf.get(1).ok_or_else(invalid)?,
+                payload: buf.split_off(2),
+            },
+            id => Message::Unknown(id),
+        };
+        Ok(msg)
+    }
+
+    pub(super) async fn write<W: AsyncWrite + Unpin>(&self, w: &mut W) -> Result<(), PeerError> {
+        w.write_all(&self.to_bytes()).await?;
+        Ok(())
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = vec![0; 4];
+        match self {
+            Message::KeepAlive => {}
+            Message::Choke => buf.push(0),
+            Message::Unchoke => buf.push(1),
+            Message::Interested => buf.push(2),
+            Message::NotInterested => buf.push(3),
+            Message::Have(index) => {
+                buf.push(4);
+                buf.extend_from_slice(&index.to_be_bytes());
+            }
+            Message::Bitfield(bits) => {
+                buf.push(5);
+                buf.extend_from_slice(bits);
+            }
+            Message::Request {
+                index,
+                begin,
+                length,
+            }
+            | Message::Cancel {
+                index,
+                begin,
+                length,
+            } => {
+                let id = match self {
+                    Message::Request { .. } => 6,
+                    _ => 8,
+                };
+                buf.push(id);
+                for n in [index, begin, length] {
+                    buf.extend_from_slice(&n.to_be_bytes());
+                }
+            }
+            Message::Piece {
+                index,
+                begin,
+                block,
+            } => {
+                buf.push(7);
+                buf.extend_from_slice(&index.to_be_bytes());
+                buf.extend_from_slice(&begin.to_be_bytes());
+                buf.extend_from_slice(block);
+            }
+            Message::Extended { id, payload } => {
+                buf.extend_from_slice(&[20, *id]);
+                buf.extend_from_slice(payload);
+            }
+            Message::Unknown(id) => buf.push(*id),
+        }
+
+        let len = (buf.len() - 4) as u32;
+        buf[..4].copy_from_slice(&len.to_be_bytes());
+        buf
+    }
+}
+
+/// An established connection to a peer, past the handshake.
+pub(super) struct PeerConn {
+    stream: TcpStream,
+    pub head: HandshakeHead,
+}
+
+impl PeerConn {
+    /// Connects to `addr` and exchanges handshakes for `info_hash`.
+    pub(super) async fn connect(
+        addr: SocketAddr,
+        info_hash: [u8; 20],
+        peer_id: [u8; 20],
+    ) -> Result<PeerConn, PeerError> {
+        let connecting = async {
+            let mut stream = TcpStream::connect(addr).await?;
+            stream.write_all(&handshake(info_hash, peer_id)).await?;
+
+            let head = HandshakeHead::read(&mut stream).await?;
+            if head.info_hash != info_hash {
+                return Err(PeerError::InfoHashMismatch);
+            }
+            // the peer id is of no use to us:
+            stream.read_exact(&mut [0; 20]).await?;
+
+            Ok(PeerConn { stream, head })
+        };
+
+        time::timeout(CONNECT_TIMEOUT, connecting)
+            .await
+            .map_err(|_| PeerError::Timeout)?
+    }
+
+    /// Finishes the handshake of a peer that connected to us, answering
+    /// under the info hash it asked for.
+    pub(super) async fn accept(
+        inbound: InboundPeer,
+        peer_id: [u8; 20],
+    ) -> Result<PeerConn, PeerError> {
+        let InboundPeer {
+            mut stream, head, ..
+        } = inbound;
+
+        let accepting = async {
+            stream
+                .write_all(&handshake(head.info_hash, peer_id))
+                .await?;
+            stream.read_exact(&mut [0; 20]).await?;
+            Ok(PeerConn { stream, head })
+        };
+
+        time::timeout(CONNECT_TIMEOUT, accepting)
+            .await
+            .map_err(|_| PeerError::Timeout)?
+    }
+
+    pub(super) async fn send(&mut self, msg: &Message) -> Result<(), PeerError> {
+        msg.write(&mut self.stream).await
+    }
+
+    pub(super) async fn recv(&mut self) -> Result<Message, PeerError> {
+        Message::read(&mut self.stream).await
+    }
+}
+
+#[cfg(test)]
+mod test_peer {
+    use super::*;
+
+    #[tokio::test]
+    async fn messages_round_trip() {
+        let messages = [
+            Message::KeepAlive,
+            Message::Unchoke,
+            Message::Have(7),
+            Message::Bitfield(vec![0xff, 0x80]),
+            Message::Request {
+                index: 1,
+                begin: 16384,
+                length: 16384,
+            },
+            Message::Cancel {
+                index: 1,
+                begin: 0,
+                length: 2,
+            },
+            Message::Piece {
+                index: 2,
+                begin: 0,
+                block: b"data".to_vec(),
+            },
+            Message::Extended {
+                id: 0,
+                payload: b"de".to_vec(),
+            },
+            Message::Unknown(9),
+        ];
+
+        let mut wire = Vec::new();
+        for msg in &messages {
+            msg.write(&mut wire).await.unwrap();
+        }
+
+        let mut r = wire.as_slice();
+        for msg in messages {
+            assert_eq!(Message::read(&mut r).await.unwrap(), msg);
+        }
+    }
+
+    #[tokio::test]
+    async fn error_on_malformed_message() {
+        let mut too_long = &[0xff, 0xff, 0xff, 0xff][..];
+        assert!(matches!(
+            Message::read(&mut too_long).await,
+            Err(PeerError::MessageTooLong(_))
+        ));
+
+        let mut short_have = &[0, 0, 0, 2, 4, 1][..];
+        assert!(matches!(
+            Message::read(&mut short_have).await,
+            Err(PeerError::InvalidMessage(4))
+        ));
+    }
+}
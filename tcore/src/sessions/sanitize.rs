@@ -0,0 +1,190 @@
+use std::{collections::HashSet, path::PathBuf};
+
+/// Longest file name most filesystems take, in bytes.
+const MAX_COMPONENT_LEN: usize = 255;
+
+/// Extensions longer than this are not kept when a name is cut or renamed.
+const MAX_EXTENSION_LEN: usize = 16;
+
+/// Device names Windows won't create a file under, whatever the extension.
+const RESERVED: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Makes one path component safe to create on any common filesystem:
+/// separators, NUL, control and Windows-only characters become `_`,
+/// trailing dots and spaces go, reserved names get a `_` prefix and long
+/// names are cut to 255 bytes keeping the extension. `..` ends up as `_`.
+pub fn sanitize_component(part: &str) -> String {
+    let mut clean: String = part
+        .chars()
+        .map(|c| match c {
+            '\0'..='\x1f' | '/' | '\\' | '<' | '>' | ':' | '"' | '|' | '?' | '*' => '_',
+            c => c,
+        })
+        .collect();
+
+    let trimmed = clean.trim_end_matches(['.', ' ']).len();
+    clean.truncate(trimmed);
+    if clean.is_empty() {
+        return "_".to_string();
+    }
+
+    let stem = clean.split('.').next().unwrap_or_default().trim_end();
+    if RESERVED.iter().any(|name| name.eq_ignore_ascii_case(stem)) {
+        clean.insert(0, '_');
+    }
+    truncate(clean)
+}
+
+/// Maps the paths of a torrent's files to relative paths that can't leave
+/// the directory they are joined to. Empty and `.` components are dropped,
+/// the rest sanitized. A file landing on an earlier file or directory,
+/// ignoring case, and a directory landing on an earlier file are renamed
+/// to `name.1.ext`, `name.2.ext`... so the result only depends on the order.
+pub fn map_paths<'a>(paths: impl IntoIterator<Item = &'a [String]>) -> Vec<PathBuf> {
+    // lowercased, case-insensitive filesystems would merge them otherwise
+    let mut files = HashSet::new();
+    let mut dirs = HashSet::new();
+
+    paths
+        .into_iter()
+        .map(|path| {
+            let mut parts: Vec<String> = path
+                .iter()
+                .filter(|part| !matches!(part.as_str(), "" | "."))
+                .map(|part| sanitize_component(part))
+                .collect();
+            if parts.is_empty() {
+                parts.push("_".to_string());
+            }
+
+            for i in 0..parts.len() {
+                let is_file = i == parts.len() - 1;
+                let original = parts[i].clone();
+                let mut n = 0;
+                let key = loop {
+                    let key = parts[..=i].join("/").to_lowercase();
+                    let taken = files.contains(&key) || (is_file && dirs.contains(&key));
+                    if !taken {
+                        break key;
+                    }
+                    n += 1;
+                    parts[i] = numbered(&original, n);
+                };
+                match is_file {
+                    true => files.insert(key),
+                    false => dirs.insert(key),
+                };
+            }
+            parts.iter().collect()
+        })
+        .collect()
+}
+
+/// `name.ext` as `name.<n>.ext`.
+fn numbered(name: &str, n: usize) -> String {
+    let (stem, ext) = split_extension(name);
+    truncate(format!("{stem}.{n}{ext}"))
+}
+
+fn truncate(mut name: String) -> String {
+    if name.len() <= MAX_COMPONENT_LEN {
+        return name;
+    }
+
+    let ext = split_extension(&name).1.to_string();
+    let mut end = MAX_COMPONENT_LEN - ext.len();
+    while !name.is_char_boundary(end) {
+        end -= 1;
+    }
+    name.truncate(end);
+    name + &ext
+}
+
+/// Splits before the last dot, if what follows looks like an extension.
+fn split_extension(name: &str) -> (&str, &str) {
+    match name.rfind('.') {
+        Some(dot) if dot > 0 && name.len() - dot <= MAX_EXTENSION_LEN => name.split_at(dot),
+        _ => (name, ""),
+    }
+}
+
+#[cfg(test)]
+mod test_sanitize {
+    use std::path::{Component, Path};
+
+    use super::*;
+
+    fn paths(paths: &[&[&str]]) -> Vec<PathBuf> {
+        let paths: Vec<Vec<String>> = paths
+            .iter()
+            .map(|path| path.iter().map(|part| part.to_string()).collect())
+            .collect();
+        map_paths(paths.iter().map(Vec::as_slice))
+    }
+
+    #[test]
+    fn components_are_made_safe() {
+        assert_eq!(sanitize_component(".."), "_");
+        assert_eq!(sanitize_component("/etc"), "_etc");
+        assert_eq!(sanitize_component("C:\\Windows"), "C__Windows");
+        assert_eq!(sanitize_component("a\0b\nc"), "a_b_c");
+        assert_eq!(sanitize_component("name. . "), "name");
+        assert_eq!(sanitize_component("con"), "_con");
+        assert_eq!(sanitize_component("LPT1.txt"), "_LPT1.txt");
+        assert_eq!(sanitize_component("console"), "console");
+        assert_eq!(sanitize_component(".hidden"), ".hidden");
+
+        let long = format!("{}.txt", "я".repeat(200));
+        let cut = sanitize_component(&long);
+        assert!(cut.len() <= MAX_COMPONENT_LEN);
+        assert!(cut.starts_with('я') && cut.ends_with("я.txt"));
+    }
+
+    #[test]
+    fn paths_stay_below_the_root() {
+        let mapped = paths(&[
+            &["..", "..", "etc", "passwd"],
+            &["/", "abs"],
+            &["", ".", "a", "", "b"],
+            &[""],
+        ]);
+
+        assert_eq!(mapped[0], Path::new("_/_/etc/passwd"));
+        assert_eq!(mapped[1], Path::new("_/abs"));
+        assert_eq!(mapped[2], Path::new("a/b"));
+        assert_eq!(mapped[3], Path::new("_.1"));
+        for path in mapped {
+            assert!(path.components().all(|c| matches!(c, Component::Normal(_))));
+        }
+    }
+
+    #[test]
+    fn collisions_are_renamed() {
+        let mapped = paths(&[
+            &["dir", "File.txt"],
+            &["dir", "file.txt"],
+            &["dir", "fi|e.txt"],
+            &["dir", "fi?e.txt"],
+            &["a"],
+            &["a", "b"],
+            &["Dir"],
+        ]);
+
+        assert_eq!(
+            mapped,
+            [
+                "dir/File.txt",
+                "dir/file.1.txt",
+                "dir/fi_e.txt",
+                "dir/fi_e.1.txt",
+                "a",
+                "a.1/b",
+                "Dir.1",
+            ]
+            .map(PathBuf::from)
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use std::{collections::HashMap, net::SocketAddr, sync::Arc};
+use std::{collections::HashMap, net::SocketAddr, sync::Arc, time::Duration};
 
 use chrono::Utc;
 use rand::RngCore;
@@ -8,9 +8,21 @@ use tokio::{
     net::{TcpListener, TcpStream},
     sync::{Mutex, mpsc},
     task::JoinHandle,
+    time,
 };
 
-use crate::{bencode::Torrent, sessions::tracker::TrackerBuilder};
+use crate::{
+    bencode::Torrent,
+    ids::{InfoHash, PeerId},
+    magnet::Magnet,
+    sessions::{
+        handshake::HandshakeHead,
+        tracker::{Source, TrackerBuilder},
+    },
+};
+
+/// How long a connecting peer has to say which torrent it wants.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
 
 pub struct Session {
     shared: Arc<SessionShared>,
@@ -18,11 +30,16 @@ pub struct Session {
     accept_join: JoinHandle<()>,
     dispath_join: JoinHandle<()>,
 
-    routes: Arc<Mutex<HashMap<[u8; 20], mpsc::Sender<TcpStream>>>>,
+    /// Hybrid torrents are routed under both their v1 and v2 hashes.
+    routes: Routes,
 }
 
+/// Workers by the 20 bytes of their info hash that peers send in the
+/// handshake, v2 hashes truncated.
+type Routes = Arc<Mutex<HashMap<[u8; 20], mpsc::Sender<InboundPeer>>>>;
+
 pub(super) struct SessionShared {
-    pub peer_id: [u8; 20],
+    pub peer_id: PeerId,
     pub http: reqwest::Client,
     pub listen_addr: SocketAddr,
     pub incoming_tx: mpsc::Sender<SessionEvent>,
@@ -40,14 +57,9 @@ impl Session {
         let incoming_tx_shared = incoming_tx.clone();
 
         let accept_join = tokio::spawn(async move {
-            loop {
-                match listener.accept().await {
-                    Ok((stream, addr)) => {
-                        let _ = incoming_tx.send(SessionEvent::NewConn(stream, addr)).await; // FIXME: how do i handle
-                        // the error?
-                    }
-                    Err(_) => break,
-                }
+            while let Ok((stream, addr)) = listener.accept().await {
+                let _ = incoming_tx.send(SessionEvent::NewConn(stream, addr)).await; // FIXME: how do i handle
+                // the error?
             }
         });
 
@@ -58,25 +70,24 @@ impl Session {
             while let Some(event) = incoming_rx.recv().await {
                 match event {
                     SessionEvent::NewConn(stream, addr) => {
-                        // TODO:
-                        // 1. read first 19 bytes
-                        // 2. make sure it says "BitTorrent Protocol"
-                        // 3. skip next 8 empty reserved bytes
-                        // 4. read next 20 bytes as info hash
-                        // 5. find a worker for this tracker
-                        todo!(
-                            "get info_hash from stream and find a worker. read more in comment below"
-                        );
+                        // reading the handshake must not hold up other events
+                        tokio::spawn(dispatch_conn(routes_clone.clone(), stream, addr));
                     }
-                    SessionEvent::RegisterWorker(key, tx) => {
+                    SessionEvent::RegisterWorker(hash, tx) => {
+                        let key = hash.truncated();
                         let mut map = routes_clone.lock().await;
-                        if map.contains_key(&key) {
-                            todo!("do something with duplicate key?")
+                        // the same torrent added twice, or a magnet and then
+                        // its .torrent: peers stay with the first worker
+                        // for as long as it runs
+                        match map.get(&key) {
+                            Some(first) if !first.is_closed() => {}
+                            _ => {
+                                map.insert(key, tx);
+                            }
                         }
-                        map.insert(key, tx);
                     }
-                    SessionEvent::UnregisterWorker(key) => {
-                        routes_clone.lock().await.remove(&key);
+                    SessionEvent::UnregisterWorker(hash) => {
+                        routes_clone.lock().await.remove(&hash.truncated());
                     }
                 }
             }
@@ -96,11 +107,31 @@ impl Session {
     }
 
     pub fn add_torrent(&self, torrent: Torrent) -> TrackerBuilder {
-        TrackerBuilder::new(self.shared.clone(), torrent)
+        TrackerBuilder::new(self.shared.clone(), Source::Torrent(Box::new(torrent)))
+    }
+
+    /// Starts from the info hash alone, the rest of the torrent is
+    /// fetched from peers (BEP 9) before the download begins.
+    pub fn add_magnet(&self, magnet: Magnet) -> TrackerBuilder {
+        TrackerBuilder::new(self.shared.clone(), Source::Magnet(magnet))
+    }
+}
+
+/// Reads the handshake head and passes the connection to the worker of the
+/// requested info hash. Connections for unknown torrents are dropped.
+async fn dispatch_conn(routes: Routes, mut stream: TcpStream, addr: SocketAddr) {
+    let head = match time::timeout(HANDSHAKE_TIMEOUT, HandshakeHead::read(&mut stream)).await {
+        Ok(Ok(head)) => head,
+        _ => return,
+    };
+
+    let tx = routes.lock().await.get(&head.info_hash).cloned();
+    if let Some(tx) = tx {
+        let _ = tx.send(InboundPeer { stream, addr, head }).await;
     }
 }
 
-fn new_peer_id() -> [u8; 20] {
+fn new_peer_id() -> PeerId {
     let ts = Utc::now()
         .timestamp_nanos_opt()
         .unwrap_or_else(|| Utc::now().timestamp());
@@ -115,13 +146,20 @@ fn new_peer_id() -> [u8; 20] {
     hasher.update(b"|");
     hasher.update(salt);
 
-    hasher.finalize().into()
+    PeerId::new(hasher.finalize().into())
 }
 
 pub(super) enum SessionEvent {
     NewConn(TcpStream, SocketAddr),
-    RegisterWorker([u8; 20], mpsc::Sender<TcpStream>),
-    UnregisterWorker([u8; 20]),
+    RegisterWorker(InfoHash, mpsc::Sender<InboundPeer>),
+    UnregisterWorker(InfoHash),
+}
+
+/// A peer that connected to us, with its handshake read up to the info hash.
+pub(super) struct InboundPeer {
+    pub stream: TcpStream,
+    pub addr: SocketAddr,
+    pub head: HandshakeHead,
 }
 
 #[derive(Error, Debug)]
@@ -129,3 +167,143 @@ pub enum SessionError {
     #[error("i/o error:{0}")]
     Io(#[from] std::io::Error),
 }
+
+#[cfg(test)]
+mod test_session {
+    use tokio::io::AsyncWriteExt;
+
+    use super::*;
+    use crate::sessions::handshake::PROTOCOL;
+
+    fn handshake_head(info_hash: [u8; 20]) -> Vec<u8> {
+        [&[19], PROTOCOL.as_slice(), &[0; 8], &info_hash].concat()
+    }
+
+    #[tokio::test]
+    async fn both_hashes_route_to_the_same_worker() {
+        let session = Session::bind().await.unwrap();
+        let (tx, mut rx) = mpsc::channel(2);
+        for hash in [InfoHash::V1([1; 20]), InfoHash::V2([2; 32])] {
+            session
+                .shared
+                .incoming_tx
+                .send(SessionEvent::RegisterWorker(hash, tx.clone()))
+                .await
+                .unwrap();
+        }
+
+        let port = session.shared.listen_addr.port();
+        for hash in [[2; 20], [1; 20]] {
+            let mut peer = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+            peer.write_all(&handshake_head(hash)).await.unwrap();
+
+            let inbound = time::timeout(Duration::from_secs(5), rx.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(inbound.head.info_hash, hash);
+        }
+    }
+
+    #[tokio::test]
+    async fn duplicate_routes_keep_the_first_worker() {
+        let session = Session::bind().await.unwrap();
+        let hash = InfoHash::V1([1; 20]);
+        let register = |tx| {
+            session
+                .shared
+                .incoming_tx
+                .send(SessionEvent::RegisterWorker(hash, tx))
+        };
+        let connect = || async {
+            let port = session.shared.listen_addr.port();
+            let mut peer = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+            peer.write_all(&handshake_head([1; 20])).await.unwrap();
+            peer
+        };
+
+        let (first_tx, mut first_rx) = mpsc::channel(1);
+        let (second_tx, mut second_rx) = mpsc::channel(1);
+        register(first_tx).await.unwrap();
+        register(second_tx).await.unwrap();
+        let _peer = connect().await;
+        time::timeout(Duration::from_secs(5), first_rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+
+        // once the first worker is gone the route is free again
+        drop(first_rx);
+        let (third_tx, mut third_rx) = mpsc::channel(1);
+        register(third_tx).await.unwrap();
+        let _peer = connect().await;
+        time::timeout(Duration::from_secs(5), third_rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(second_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn magnet_bootstraps_from_seeding_session() {
+        let data = concat!(
+            "d8:announce27:http://127.0.0.1:1/announce4:infod",
+            "6:lengthi1e4:name4:test12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaa",
+            "ee"
+        );
+        let torrent = Torrent::from_bytes(data.as_bytes()).unwrap();
+        let mut magnet = Magnet::from_torrent(&torrent);
+
+        let seeding = Session::bind().await.unwrap();
+        let _seed = seeding.add_torrent(torrent).begin().await.unwrap();
+        magnet.peers = vec![format!("127.0.0.1:{}", seeding.shared.listen_addr.port())];
+
+        let session = Session::bind().await.unwrap();
+        let tracker = session.add_magnet(magnet).begin().await.unwrap();
+
+        time::timeout(Duration::from_secs(5), async {
+            while tracker.status().fetching_metadata {
+                time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .expect("metadata is fetched from the other session");
+    }
+
+    #[tokio::test]
+    async fn private_flag_is_exposed() {
+        let data = concat!(
+            "d8:announce27:http://127.0.0.1:1/announce4:infod",
+            "6:lengthi1e4:name4:test12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaa",
+            "7:privatei1eee"
+        );
+        let torrent = Torrent::from_bytes(data.as_bytes()).unwrap();
+
+        let session = Session::bind().await.unwrap();
+        let tracker = session.add_torrent(torrent).begin().await.unwrap();
+
+        assert!(tracker.status().private);
+    }
+
+    #[tokio::test]
+    async fn unknown_hash_is_dropped() {
+        let session = Session::bind().await.unwrap();
+        let (tx, mut rx) = mpsc::channel(1);
+        session
+            .shared
+            .incoming_tx
+            .send(SessionEvent::RegisterWorker(InfoHash::V1([1; 20]), tx))
+            .await
+            .unwrap();
+
+        let port = session.shared.listen_addr.port();
+        let mut peer = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        peer.write_all(&handshake_head([3; 20])).await.unwrap();
+
+        assert!(
+            time::timeout(Duration::from_millis(200), rx.recv())
+                .await
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,315 @@
+use std::{
+    collections::HashMap,
+    fs::{self, OpenOptions},
+    io::{self, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    bencode::{Info, Layout},
+    sessions::sanitize::{map_paths, sanitize_component},
+};
+
+/// A file of the torrent and where it goes on disk.
+struct FileEntry {
+    path: PathBuf,
+    length: u64,
+    kind: FileKind,
+}
+
+/// What BEP 47 attributes make of a file.
+enum FileKind {
+    Regular {
+        executable: bool,
+    },
+    /// Zeros aligning the next file to a piece, never written to disk.
+    Padding,
+    /// Target as seen from the link's directory, none when it would point
+    /// out of the torrent's directory.
+    Symlink(Option<PathBuf>),
+}
+
+/// Maps the pieces of a v1 torrent onto its files in the download directory:
+/// `<save_to>/<name>` for a single file, `<save_to>/<name>/<path>` otherwise.
+/// Names and paths are sanitized, nothing lands outside of `save_to`.
+pub(super) struct Storage {
+    files: Vec<FileEntry>,
+    layout: Layout<'static>,
+}
+
+impl Storage {
+    pub(super) fn new(info: &Info, save_to: &Path) -> Storage {
+        let root = save_to.join(sanitize_component(&info.name));
+        let files = match &info.files {
+            Some(files) => {
+                // padding is never written, it must not push real files aside
+                let real: Vec<_> = files.iter().filter(|file| !file.is_padding()).collect();
+                let paths = map_paths(real.iter().map(|file| file.path()));
+                let mapped: HashMap<&[String], &PathBuf> =
+                    real.iter().map(|file| file.path()).zip(&paths).collect();
+
+                let mut paths = paths.iter();
+                files
+                    .iter()
+                    .map(|file| {
+                        let (path, kind) = if file.is_padding() {
+                            (PathBuf::new(), FileKind::Padding)
+                        } else {
+                            let path = paths.next().unwrap();
+                            let kind = match file.is_symlink() {
+                                true => {
+                                    let target = file.symlink_path().unwrap_or_default();
+                                    FileKind::Symlink(link_target(path, target, &mapped))
+                                }
+                                false => FileKind::Regular {
+                                    executable: file.is_executable(),
+                                },
+                            };
+                            (root.join(path), kind)
+                        };
+                        FileEntry {
+                            path,
+                            length: file.length(),
+                            kind,
+                        }
+                    })
+                    .collect()
+            }
+            None => vec![FileEntry {
+                path: root,
+                length: info.total_length(),
+                kind: FileKind::Regular { executable: false },
+            }],
+        };
+
+        Storage {
+            files,
+            layout: info.layout().into_owned(),
+        }
+    }
+
+    pub(super) fn layout(&self) -> &Layout<'static> {
+        &self.layout
+    }
+
+    /// Padding files are all zeros, nobody serves them.
+    pub(super) fn is_padding(&self, file: usize) -> bool {
+        matches!(self.files[file].kind, FileKind::Padding)
+    }
+
+    /// Writes a verified piece to its files, creating them as needed.
+    pub(super) fn write_piece(&self, piece: usize, data: &[u8]) -> io::Result<()> {
+        let mut data = data;
+        for slice in self.layout.file_slices(piece) {
+            let (head, rest) = data.split_at(slice.length as usize);
+            data = rest;
+            if self.is_padding(slice.file) {
+                continue;
+            }
+
+            let path = &self.files[slice.file].path;
+            create_parent(path)?;
+
+            let mut file = OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(path)?;
+            file.seek(SeekFrom::Start(slice.offset))?;
+            file.write_all(head)?;
+        }
+        Ok(())
+    }
+
+    /// Creates what no piece covers, empty files and symlinks, and sets the
+    /// executable bits once every piece is written.
+    pub(super) fn finish(&self) -> io::Result<()> {
+        for file in &self.files {
+            match &file.kind {
+                FileKind::Regular { executable } => {
+                    if file.length == 0 {
+                        create_parent(&file.path)?;
+                        OpenOptions::new()
+                            .write(true)
+                            .create(true)
+                            .truncate(false)
+                            .open(&file.path)?;
+                    }
+                    if *executable {
+                        set_executable(&file.path)?;
+                    }
+                }
+                FileKind::Symlink(Some(target)) => {
+                    create_parent(&file.path)?;
+                    if fs::symlink_metadata(&file.path).is_ok() {
+                        fs::remove_file(&file.path)?;
+                    }
+                    symlink(target, &file.path)?;
+                }
+                FileKind::Padding | FileKind::Symlink(None) => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Target of the link saved at `link` as seen from the link's own directory.
+/// `target` is a path of the torrent, saved where that file went or mapped
+/// like any other path. Targets going up with `..` are refused so the link
+/// can't leave the torrent's directory.
+fn link_target(
+    link: &Path,
+    target: &[String],
+    mapped: &HashMap<&[String], &PathBuf>,
+) -> Option<PathBuf> {
+    if target.is_empty() || target.iter().any(|part| part == "..") {
+        return None;
+    }
+    let target = match mapped.get(target) {
+        Some(path) => path.to_path_buf(),
+        None => map_paths([target]).remove(0),
+    };
+
+    let mut relative = PathBuf::new();
+    for _ in 1..link.components().count() {
+        relative.push("..");
+    }
+    relative.push(target);
+    Some(relative)
+}
+
+fn create_parent(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(dir) => fs::create_dir_all(dir),
+        None => Ok(()),
+    }
+}
+
+#[cfg(unix)]
+fn set_executable(path: &Path) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mut permissions = fs::metadata(path)?.permissions();
+    // executable by whoever can read it
+    let mode = permissions.mode();
+    permissions.set_mode(mode | (mode & 0o444) >> 2);
+    fs::set_permissions(path, permissions)
+}
+
+#[cfg(not(unix))]
+fn set_executable(_: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+fn symlink(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(not(unix))]
+fn symlink(_: &Path, _: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod test_storage {
+    use crate::bencode::Torrent;
+
+    use super::*;
+
+    /// Files of 3, 0 and 7 bytes in pieces of 4.
+    fn info() -> Info {
+        let data = concat!(
+            "d8:announce1:x4:infod5:filesl",
+            "d6:lengthi3e4:pathl1:aee",
+            "d6:lengthi0e4:pathl5:emptyee",
+            "d6:lengthi7e4:pathl3:dir1:bee",
+            "e4:name4:test12:piece lengthi4e6:pieces60:",
+            "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+            "ee"
+        );
+        Torrent::from_bytes(data.as_bytes()).unwrap().info
+    }
+
+    #[test]
+    fn pieces_are_written_to_their_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(&info(), dir.path());
+
+        storage.write_piece(2, b"fg").unwrap();
+        storage.write_piece(0, b"123a").unwrap();
+        storage.write_piece(1, b"bcde").unwrap();
+
+        let root = dir.path().join("test");
+        assert_eq!(fs::read(root.join("a")).unwrap(), b"123");
+        assert_eq!(fs::read(root.join("dir").join("b")).unwrap(), b"abcdefg");
+    }
+
+    #[test]
+    fn attributes_are_applied() {
+        let data = concat!(
+            "d8:announce1:x4:infod5:filesl",
+            "d4:attr1:x6:lengthi2e4:pathl3:runee",
+            "d4:attr1:p6:lengthi2e4:pathl4:.pad1:2ee",
+            "d6:lengthi4e4:pathl3:dir4:dataee",
+            "d4:attr1:l6:lengthi0e4:pathl3:dir4:linke12:symlink pathl3:runee",
+            "d4:attr1:l6:lengthi0e4:pathl6:escapee12:symlink pathl2:..6:secretee",
+            "e4:name4:test12:piece lengthi4e6:pieces40:",
+            "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+            "ee"
+        );
+        let info = Torrent::from_bytes(data.as_bytes()).unwrap().info;
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(&info, dir.path());
+
+        storage.write_piece(0, b"#!\0\0").unwrap();
+        storage.write_piece(1, b"data").unwrap();
+        storage.finish().unwrap();
+
+        let root = dir.path().join("test");
+        assert!(!root.join(".pad").exists());
+        assert_eq!(fs::read(root.join("run")).unwrap(), b"#!");
+        assert_eq!(fs::read(root.join("dir").join("link")).unwrap(), b"#!");
+        assert_eq!(
+            fs::read_link(root.join("dir").join("link")).unwrap(),
+            Path::new("../run")
+        );
+        assert!(fs::symlink_metadata(root.join("escape")).is_err());
+        assert!(fs::symlink_metadata(dir.path().join("secret")).is_err());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(root.join("run")).unwrap().permissions().mode();
+            assert_eq!(mode & 0o100, 0o100);
+        }
+    }
+
+    #[test]
+    fn hostile_paths_stay_in_save_to() {
+        let data = concat!(
+            "d8:announce1:x4:infod5:filesl",
+            "d6:lengthi2e4:pathl2:..2:..4:evilee",
+            "d6:lengthi2e4:pathl5:/evilee",
+            "d6:lengthi2e4:pathl4:EVILee",
+            "e4:name2:..12:piece lengthi6e6:pieces20:",
+            "aaaaaaaaaaaaaaaaaaaa",
+            "ee"
+        );
+        let info = Torrent::from_bytes(data.as_bytes()).unwrap().info;
+        let parent = tempfile::tempdir().unwrap();
+        let save_to = parent.path().join("save");
+        let storage = Storage::new(&info, &save_to);
+
+        storage.write_piece(0, b"112233").unwrap();
+
+        let root = save_to.join("_");
+        assert_eq!(
+            fs::read(root.join("_").join("_").join("evil")).unwrap(),
+            b"11"
+        );
+        assert_eq!(fs::read(root.join("_evil")).unwrap(), b"22");
+        assert_eq!(fs::read(root.join("EVIL")).unwrap(), b"33");
+        assert_eq!(fs::read_dir(parent.path()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,98 @@
+use rand::seq::SliceRandom;
+
+/// Position of a tracker: its tier and its place inside the tier.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) struct TrackerPos {
+    pub tier: usize,
+    pub index: usize,
+}
+
+/// Trackers grouped in tiers as described in BEP 12.
+///
+/// Trackers are shuffled inside every tier once, then tried in order,
+/// tier after tier. The one that answered is moved to the front of its tier.
+pub(super) struct TrackerTiers {
+    tiers: Vec<Vec<String>>,
+}
+
+impl TrackerTiers {
+    pub(super) fn new(mut tiers: Vec<Vec<String>>) -> TrackerTiers {
+        tiers.retain(|tier| !tier.is_empty());
+
+        let mut rng = rand::rng();
+        for tier in &mut tiers {
+            tier.shuffle(&mut rng);
+        }
+        TrackerTiers { tiers }
+    }
+
+    /// All trackers in the order they should be tried.
+    pub(super) fn iter(&self) -> impl Iterator<Item = (TrackerPos, &str)> {
+        self.tiers.iter().enumerate().flat_map(|(tier, urls)| {
+            urls.iter()
+                .enumerate()
+                .map(move |(index, url)| (TrackerPos { tier, index }, url.as_str()))
+        })
+    }
+
+    /// Moves a tracker that answered to the front of its tier.
+    pub(super) fn promote(&mut self, pos: TrackerPos) {
+        if let Some(tier) = self.tiers.get_mut(pos.tier)
+            && pos.index < tier.len()
+        {
+            let url = tier.remove(pos.index);
+            tier.insert(0, url);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_tiers {
+    use super::*;
+
+    fn tiers(tiers: &[&[&str]]) -> TrackerTiers {
+        TrackerTiers {
+            tiers: tiers
+                .iter()
+                .map(|tier| tier.iter().map(|url| url.to_string()).collect())
+                .collect(),
+        }
+    }
+
+    fn urls(tiers: &TrackerTiers) -> Vec<&str> {
+        tiers.iter().map(|(_, url)| url).collect()
+    }
+
+    #[test]
+    fn tiers_are_tried_in_order() {
+        let tiers = tiers(&[&["a", "b"], &["c"]]);
+
+        assert_eq!(urls(&tiers), ["a", "b", "c"]);
+        assert_eq!(
+            tiers.iter().nth(2).unwrap().0,
+            TrackerPos { tier: 1, index: 0 }
+        );
+    }
+
+    #[test]
+    fn promote_moves_to_the_front_of_its_tier() {
+        let mut tiers = tiers(&[&["a"], &["b", "c", "d"]]);
+        tiers.promote(TrackerPos { tier: 1, index: 2 });
+
+        assert_eq!(urls(&tiers), ["a", "d", "b", "c"]);
+    }
+
+    #[test]
+    fn shuffle_keeps_trackers_in_their_tiers() {
+        let tiers = TrackerTiers::new(vec![
+            vec!["a".into(), "b".into(), "c".into()],
+            vec![],
+            vec!["d".into()],
+        ]);
+
+        let mut first: Vec<&str> = urls(&tiers)[..3].to_vec();
+        first.sort();
+        assert_eq!(first, ["a", "b", "c"]);
+        assert_eq!(urls(&tiers)[3], "d");
+    }
+}
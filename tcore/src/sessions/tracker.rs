@@ -1,30 +1,70 @@
-use std::{path::PathBuf, sync::Arc};
+use std::{fmt::Display, net::SocketAddr, path::PathBuf, sync::Arc};
 
 use thiserror::Error;
 use tokio::{
-    net::TcpStream,
     sync::{mpsc, watch},
     task::JoinHandle,
 };
 
 use crate::{
     bencode::Torrent,
+    ids::InfoHash,
+    magnet::Magnet,
     sessions::{
-        session::{SessionEvent, SessionShared},
+        session::{InboundPeer, SessionEvent, SessionShared},
         worker::Worker,
     },
 };
 
 pub struct TrackerBuilder {
-    pub(super) torrent: Torrent,
+    pub(super) source: Source,
     pub(super) save_to: PathBuf,
     pub(super) session: Arc<SessionShared>,
 }
 
+pub(super) enum Source {
+    Torrent(Box<Torrent>),
+    /// Only the info hash is known until the metadata is fetched from peers.
+    Magnet(Magnet),
+}
+
+impl Source {
+    pub(super) fn info_hashes(&self) -> Vec<InfoHash> {
+        match self {
+            Source::Torrent(torrent) => torrent.info_hashes(),
+            Source::Magnet(magnet) => magnet.info_hashes(),
+        }
+    }
+
+    pub(super) fn announce_tiers(&self) -> Vec<Vec<String>> {
+        match self {
+            Source::Torrent(torrent) => torrent.announce_tiers(),
+            Source::Magnet(magnet) if magnet.trackers.is_empty() => Vec::new(),
+            Source::Magnet(magnet) => vec![magnet.trackers.clone()],
+        }
+    }
+
+    /// Servers holding the files (BEP 19).
+    pub(super) fn web_seeds(&self) -> &[String] {
+        match self {
+            Source::Torrent(torrent) => &torrent.url_list,
+            Source::Magnet(magnet) => &magnet.web_seeds,
+        }
+    }
+
+    /// Peers known without asking a tracker. Hostnames are not resolved.
+    pub(super) fn peers(&self) -> Vec<SocketAddr> {
+        match self {
+            Source::Torrent(_) => Vec::new(),
+            Source::Magnet(magnet) => magnet.peers.iter().filter_map(|p| p.parse().ok()).collect(),
+        }
+    }
+}
+
 impl TrackerBuilder {
-    pub(super) fn new(session: Arc<SessionShared>, torrent: Torrent) -> TrackerBuilder {
+    pub(super) fn new(session: Arc<SessionShared>, source: Source) -> TrackerBuilder {
         TrackerBuilder {
-            torrent,
+            source,
             save_to: "./".into(),
             session,
         }
@@ -39,20 +79,35 @@ impl TrackerBuilder {
         let (command_tx, command_rx) = mpsc::channel::<Command>(32);
         let (status_tx, status_rx) = watch::channel(TrackerStatus::default());
 
-        let (stream_tx, stream_rx) = mpsc::channel::<TcpStream>(1024);
+        let (stream_tx, stream_rx) = mpsc::channel::<InboundPeer>(1024);
+
+        let info_hashes = self.source.info_hashes();
+        if info_hashes.is_empty() {
+            return Err(TrackerError::InvalidTorrent);
+        }
 
-        let info_hash = match self.torrent.info_hash() {
-            Some(hash) => hash,
-            None => return Err(TrackerError::InvalidTorrent),
-        };
+        if self.source.announce_tiers().is_empty()
+            && self.source.peers().is_empty()
+            && self.source.web_seeds().is_empty()
+        {
+            return Err(TrackerError::NoTrackers);
+        }
 
-        let _ = self
-            .session
-            .incoming_tx
-            .send(SessionEvent::RegisterWorker(info_hash, stream_tx))
-            .await;
+        for info_hash in info_hashes {
+            let _ = self
+                .session
+                .incoming_tx
+                .send(SessionEvent::RegisterWorker(info_hash, stream_tx.clone()))
+                .await;
+        }
 
-        let mut worker = Worker::new(command_rx, status_tx, stream_rx, self);
+        let mut worker = Worker::new(
+            command_rx,
+            status_tx,
+            stream_rx,
+            stream_tx.downgrade(),
+            self,
+        );
 
         let join = tokio::spawn(async move {
             worker.work().await;
@@ -80,6 +135,12 @@ pub struct TrackerStatus {
     pub peers: u32,
     pub seeds: u32,
     pub is_finished: bool,
+    /// A magnet link waits for peers to send the info dict.
+    pub fetching_metadata: bool,
+    /// Peers come from the torrent's trackers only (BEP 27).
+    pub private: bool,
+    /// What went wrong last, the worker keeps going past it.
+    pub last_error: Option<String>,
 }
 
 impl TrackerStatus {
@@ -102,6 +163,18 @@ impl TrackerStatus {
     pub(super) fn finish(&mut self) {
         self.is_finished = true
     }
+
+    pub(super) fn set_fetching_metadata(&mut self, fetching: bool) {
+        self.fetching_metadata = fetching
+    }
+
+    pub(super) fn set_private(&mut self, private: bool) {
+        self.private = private
+    }
+
+    pub(super) fn set_error(&mut self, error: impl Display) {
+        self.last_error = Some(error.to_string())
+    }
 }
 
 pub struct Tracker {
@@ -120,4 +193,6 @@ impl Tracker {
 pub enum TrackerError {
     #[error("invalid torrent file")]
     InvalidTorrent,
+    #[error("torrent has no trackers, peers or web seeds")]
+    NoTrackers,
 }
@@ -0,0 +1,281 @@
+use std::time::Duration;
+
+use reqwest::{StatusCode, header::RANGE};
+use thiserror::Error;
+use tokio::time::Instant;
+use url::Url;
+
+use crate::{bencode::Info, cryptos::hash::make_sha1, sessions::storage::Storage};
+
+/// How long a seed that failed is left alone, doubled after each failure in
+/// a row up to `MAX_BACKOFF`.
+const MIN_BACKOFF: Duration = Duration::from_secs(5);
+const MAX_BACKOFF: Duration = Duration::from_secs(30 * 60);
+
+/// An HTTP server holding the files of the torrent (BEP 19).
+pub(super) struct WebSeed {
+    url: Url,
+    next_try: Instant,
+    backoff: Duration,
+}
+
+#[derive(Error, Debug)]
+pub(super) enum WebSeedError {
+    #[error("request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("expected {expected} bytes, got {got}")]
+    ShortBody { expected: u64, got: u64 },
+    #[error("piece {0} does not match its hash")]
+    HashMismatch(usize),
+}
+
+impl WebSeed {
+    pub(super) fn new(url: &str) -> Result<WebSeed, url::ParseError> {
+        Ok(WebSeed {
+            url: Url::parse(url)?,
+            next_try: Instant::now(),
+            backoff: MIN_BACKOFF,
+        })
+    }
+
+    pub(super) fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Whether the seed is out of its backoff.
+    pub(super) fn is_ready(&self, now: Instant) -> bool {
+        now >= self.next_try
+    }
+
+    /// Leaves the seed alone for a while, twice as long as the last time.
+    pub(super) fn back_off(&mut self) {
+        self.next_try = Instant::now() + self.backoff;
+        self.backoff = (self.backoff * 2).min(MAX_BACKOFF);
+    }
+
+    pub(super) fn reset_backoff(&mut self) {
+        self.backoff = MIN_BACKOFF;
+    }
+
+    /// A url ending in `/` is a directory the torrent's name (and path) is
+    /// appended to. Otherwise it points at the single file itself, or at the
+    /// directory of a multi-file torrent.
+    fn file_url(&self, info: &Info, file: usize) -> Url {
+        let mut url = self.url.clone();
+        if info.files.is_none() && !url.path().ends_with('/') {
+            return url;
+        }
+
+        if let Ok(mut segments) = url.path_segments_mut() {
+            segments.pop_if_empty().push(&info.name);
+            if let Some(files) = &info.files {
+                segments.extend(files[file].path());
+            }
+        }
+        url
+    }
+
+    /// Downloads a piece with one range request per file it overlaps and
+    /// checks it against its hash.
+    pub(super) async fn fetch_piece(
+        &self,
+        http: &reqwest::Client,
+        info: &Info,
+        storage: &Storage,
+        piece: usize,
+    ) -> Result<Vec<u8>, WebSeedError> {
+        let layout = storage.layout();
+        let mut data = Vec::with_capacity(layout.piece_len(piece) as usize);
+
+        for slice in layout.file_slices(piece) {
+            if storage.is_padding(slice.file) {
+                data.resize(data.len() + slice.length as usize, 0);
+                continue;
+            }
+
+            let end = slice.offset + slice.length;
+            let response = http
+                .get(self.file_url(info, slice.file))
+                .header(RANGE, format!("bytes={}-{}", slice.offset, end - 1))
+                .send()
+                .await?
+                .error_for_status()?;
+            let partial = response.status() == StatusCode::PARTIAL_CONTENT;
+            let body = response.bytes().await?;
+
+            // a server ignoring the range sends the whole file
+            let (from, to) = match partial {
+                true => (0, slice.length),
+                false => (slice.offset, end),
+            };
+            let got = body
+                .get(from as usize..to as usize)
+                .ok_or(WebSeedError::ShortBody {
+                    expected: to,
+                    got: body.len() as u64,
+                })?;
+            data.extend_from_slice(got);
+        }
+
+        if layout.piece_hash(piece) != Some(make_sha1(&data)) {
+            return Err(WebSeedError::HashMismatch(piece));
+        }
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod test_webseed {
+    use std::{fs, path::PathBuf, time::Duration};
+
+    use tokio::time;
+    use wiremock::{MockServer, Request, Respond, ResponseTemplate};
+
+    use crate::{bencode::Torrent, sessions::session::Session};
+
+    use super::*;
+
+    /// Serves the files of a directory, honouring single ranges.
+    struct ServeDir(PathBuf);
+
+    impl Respond for ServeDir {
+        fn respond(&self, req: &Request) -> ResponseTemplate {
+            let path = req
+                .url
+                .path_segments()
+                .into_iter()
+                .flatten()
+                .fold(self.0.clone(), |p, part| p.join(part));
+            let Ok(data) = fs::read(path) else {
+                return ResponseTemplate::new(404);
+            };
+
+            let range = req
+                .headers
+                .get("range")
+                .and_then(|range| range.to_str().ok()?.strip_prefix("bytes="))
+                .and_then(|range| range.split_once('-'));
+            match range {
+                Some((from, to)) => {
+                    let (from, to): (usize, usize) = (from.parse().unwrap(), to.parse().unwrap());
+                    ResponseTemplate::new(206).set_body_bytes(&data[from..=to])
+                }
+                None => ResponseTemplate::new(200).set_body_bytes(data),
+            }
+        }
+    }
+
+    /// Files of 3 and 7 bytes in pieces of 4, with the hashes of "abcd",
+    /// "efgh" and "ij".
+    fn torrent(url_list: &str) -> Torrent {
+        let pieces = [&b"abcd"[..], b"efgh", b"ij"].map(make_sha1).concat();
+        let data = [
+            format!("d8:url-list{}:{url_list}", url_list.len()).as_bytes(),
+            b"4:infod5:filesl",
+            b"d6:lengthi3e4:pathl1:aee",
+            b"d6:lengthi7e4:pathl3:dir1:bee",
+            b"e4:name4:test12:piece lengthi4e6:pieces60:",
+            &pieces,
+            b"ee",
+        ]
+        .concat();
+        Torrent::from_bytes(&data).unwrap()
+    }
+
+    fn info() -> Info {
+        torrent("http://seed/").info
+    }
+
+    async fn serve(files: &[(&str, &[u8])]) -> (MockServer, tempfile::TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+        for (path, data) in files {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, data).unwrap();
+        }
+
+        let server = MockServer::start().await;
+        wiremock::Mock::given(wiremock::matchers::any())
+            .respond_with(ServeDir(dir.path().to_owned()))
+            .mount(&server)
+            .await;
+        (server, dir)
+    }
+
+    #[test]
+    fn file_urls() {
+        let mut info = info();
+        let seed = WebSeed::new("http://host/seeds/").unwrap();
+        assert_eq!(
+            seed.file_url(&info, 1).as_str(),
+            "http://host/seeds/test/dir/b"
+        );
+
+        let seed = WebSeed::new("http://host/seeds").unwrap();
+        assert_eq!(seed.file_url(&info, 0).as_str(), "http://host/seeds/test/a");
+
+        info.files = None;
+        info.name = "a b".to_string();
+        assert_eq!(seed.file_url(&info, 0).as_str(), "http://host/seeds");
+        let seed = WebSeed::new("http://host/").unwrap();
+        assert_eq!(seed.file_url(&info, 0).as_str(), "http://host/a%20b");
+    }
+
+    #[tokio::test]
+    async fn piece_across_files() {
+        let (server, _dir) = serve(&[("test/a", b"abc"), ("test/dir/b", b"defghij")]).await;
+        let info = info();
+        let storage = Storage::new(&info, "/tmp".as_ref());
+        let seed = WebSeed::new(&format!("{}/", server.uri())).unwrap();
+        let http = reqwest::Client::new();
+
+        for (piece, expected) in [&b"abcd"[..], b"efgh", b"ij"].into_iter().enumerate() {
+            let data = seed
+                .fetch_piece(&http, &info, &storage, piece)
+                .await
+                .unwrap();
+            assert_eq!(data, expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn error_on_corrupt_piece() {
+        let (server, _dir) = serve(&[("test/a", b"abc"), ("test/dir/b", b"Xefghij")]).await;
+        let info = info();
+        let storage = Storage::new(&info, "/tmp".as_ref());
+        let seed = WebSeed::new(&format!("{}/", server.uri())).unwrap();
+        let http = reqwest::Client::new();
+
+        assert!(matches!(
+            seed.fetch_piece(&http, &info, &storage, 0).await,
+            Err(WebSeedError::HashMismatch(0))
+        ));
+    }
+
+    #[tokio::test]
+    async fn download_without_peers() {
+        let (server, _dir) = serve(&[("test/a", b"abc"), ("test/dir/b", b"defghij")]).await;
+        let save_to = tempfile::tempdir().unwrap();
+
+        let session = Session::bind().await.unwrap();
+        let tracker = session
+            .add_torrent(torrent(&format!("{}/", server.uri())))
+            .save_to(save_to.path())
+            .begin()
+            .await
+            .unwrap();
+
+        time::timeout(Duration::from_secs(5), async {
+            while !tracker.status().is_finished {
+                time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .expect("every piece comes from the web seed");
+
+        let root = save_to.path().join("test");
+        assert_eq!(fs::read(root.join("a")).unwrap(), b"abc");
+        assert_eq!(fs::read(root.join("dir").join("b")).unwrap(), b"defghij");
+        assert_eq!(tracker.status().progress, 1.0);
+    }
+}
@@ -1,25 +1,80 @@
 use std::{
-    fmt::{Display, write},
-    time::Duration,
+    collections::HashSet, fmt::Display, net::SocketAddr, path::PathBuf, sync::Arc, time::Duration,
 };
 
+use crate::{
+    bencode::Info,
+    ids::{InfoHash, PeerId},
+    sessions::{
+        announce::{AnnounceError, AnnounceResponse},
+        metadata::{MetadataError, MetadataFetch, fetch_from, serve_to},
+        peer::{PeerConn, PeerError},
+        session::{InboundPeer, SessionEvent},
+        storage::Storage,
+        tiers::{TrackerPos, TrackerTiers},
+        tracker::{Command, Source, TrackerBuilder, TrackerStatus},
+        webseed::WebSeed,
+    },
+};
 use tokio::{
-    net::TcpStream,
     sync::{mpsc, watch},
-    time,
+    task::JoinSet,
+    time::{self, Instant},
 };
-use url::form_urlencoded;
 
-use crate::sessions::tracker::{Command, TrackerBuilder, TrackerStatus};
+/// Reported as `left` while the size is unknown, a seed would not be
+/// sent other seeds.
+const UNKNOWN_LEFT: u64 = 16 * 1024;
+
+/// Next announce when no tracker answered.
+const ANNOUNCE_RETRY: Duration = Duration::from_secs(60);
+
+/// Peers asked for the metadata at the same time.
+const METADATA_PEERS: usize = 4;
+
+/// How long a round of peers gets to hand over the metadata.
+const METADATA_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Pieces asked from the web seeds in one tick.
+const WEB_SEED_BATCH: usize = 16;
 
 pub struct Worker {
     command_rx: mpsc::Receiver<Command>,
     status_tx: watch::Sender<TrackerStatus>,
-    stream_rx: mpsc::Receiver<TcpStream>,
+    stream_rx: mpsc::Receiver<InboundPeer>,
+    /// Routes hashes only the metadata tells to this worker. Weak, so the
+    /// streams still end along with the session.
+    stream_tx: mpsc::WeakSender<InboundPeer>,
+    session_tx: mpsc::Sender<SessionEvent>,
 
-    base_url: String,
+    /// Escaped hashes to announce under, two of them for hybrid torrents.
+    info_hashes: Vec<String>,
+    /// Static part of the announce query, same for every tracker.
+    query: String,
+    tiers: TrackerTiers,
+    next_announce: Instant,
     http: reqwest::Client,
 
+    peer_id: PeerId,
+    /// From the trackers.
+    peers: Vec<SocketAddr>,
+    /// From anywhere else: the magnet link's `x.pe`, later PEX or DHT.
+    /// Dropped once the metadata shows the torrent is private (BEP 27).
+    extra_peers: Vec<SocketAddr>,
+    metadata: Metadata,
+    /// The current round of peers racing to hand over the metadata.
+    metadata_round: JoinSet<(SocketAddr, Result<Info, MetadataError>)>,
+    /// Peers asked for the metadata since the last announce.
+    metadata_asked: HashSet<SocketAddr>,
+    /// Peers that connected to us, dropped along with the worker.
+    inbound: JoinSet<()>,
+    web_seeds: Vec<WebSeed>,
+
+    save_to: PathBuf,
+    /// Laid out once the metadata is known.
+    storage: Option<Storage>,
+    have: Vec<bool>,
+
     uploaded: u64,
     downloaded: u64,
     left: u64,
@@ -45,6 +100,12 @@ enum TrackerState {
     Empty,
 }
 
+enum Metadata {
+    Fetching(MetadataFetch),
+    /// Shared with the inbound peers fetching it from us.
+    Ready(Arc<Info>),
+}
+
 impl Display for TrackerState {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -60,34 +121,70 @@ impl Worker {
     pub fn new(
         command_rx: mpsc::Receiver<Command>,
         status_tx: watch::Sender<TrackerStatus>,
-        stream_rx: mpsc::Receiver<TcpStream>,
+        stream_rx: mpsc::Receiver<InboundPeer>,
+        stream_tx: mpsc::WeakSender<InboundPeer>,
         context: TrackerBuilder,
     ) -> Worker {
-        let base_url = format!(
-            "{0}?info_hash={1}&peer_id={2}&port={3}",
-            context.torrent.announce,
-            escape_hash(
-                context
-                    .torrent
-                    .info_hash()
-                    .expect("torrent file must be decoded at this moment")
-            ),
-            escape_hash(context.session.peer_id),
+        let query = format!(
+            "peer_id={0}&port={1}",
+            context.session.peer_id.url_encoded(),
             context.session.listen_addr.port(),
         );
 
-        Worker {
+        let info_hashes = context.source.info_hashes();
+        let tiers = TrackerTiers::new(context.source.announce_tiers());
+        let extra_peers = context.source.peers();
+        let web_seeds = context
+            .source
+            .web_seeds()
+            .iter()
+            .filter_map(|url| WebSeed::new(url).ok())
+            .collect();
+
+        let (metadata, left) = match context.source {
+            Source::Torrent(torrent) => {
+                let left = torrent.total_length();
+                (Metadata::Ready(Arc::new(torrent.info)), left)
+            }
+            Source::Magnet(magnet) => {
+                status_tx.send_modify(|status| status.set_fetching_metadata(true));
+                (
+                    Metadata::Fetching(MetadataFetch::new(&magnet)),
+                    UNKNOWN_LEFT,
+                )
+            }
+        };
+
+        let mut worker = Worker {
             command_rx,
             status_tx,
             stream_rx,
-            base_url,
+            stream_tx,
+            session_tx: context.session.incoming_tx.clone(),
+            info_hashes: info_hashes.iter().map(InfoHash::url_encoded).collect(),
+            query,
+            tiers,
+            next_announce: Instant::now(),
             http: context.session.http.clone(),
+            peer_id: context.session.peer_id,
+            peers: Vec::new(),
+            extra_peers,
+            metadata,
+            metadata_round: JoinSet::new(),
+            metadata_asked: HashSet::new(),
+            inbound: JoinSet::new(),
+            web_seeds,
+            save_to: context.save_to,
+            storage: None,
+            have: Vec::new(),
             worker_state: WorkerState::Running,
             uploaded: 0,
             downloaded: 0,
-            left: context.torrent.total_length(),
+            left,
             tracker_state: TrackerState::Started,
-        }
+        };
+        worker.on_metadata();
+        worker
     }
 
     pub async fn work(&mut self) {
@@ -105,7 +202,10 @@ impl Worker {
             match self.worker_state {
                 WorkerState::Paused => time::sleep(Duration::from_millis(500)).await,
                 WorkerState::Aborted => break,
-                WorkerState::Running => self.tick().await,
+                WorkerState::Running => {
+                    self.tick().await;
+                    self.idle(Duration::from_millis(500)).await
+                }
             }
 
             // 3. update tracker status
@@ -113,6 +213,45 @@ impl Worker {
         }
     }
 
+    /// Waits for the next tick, taking in the peers that connect meanwhile.
+    async fn idle(&mut self, duration: Duration) {
+        let sleep = time::sleep(duration);
+        tokio::pin!(sleep);
+
+        loop {
+            tokio::select! {
+                _ = &mut sleep => break,
+                inbound = self.stream_rx.recv() => match inbound {
+                    Some(inbound) => self.accept_peer(inbound),
+                    // the session is gone, nobody connects anymore
+                    None => break sleep.await,
+                },
+            }
+        }
+        while self.inbound.try_join_next().is_some() {}
+    }
+
+    fn accept_peer(&mut self, inbound: InboundPeer) {
+        // nothing to share before the metadata is here
+        let Metadata::Ready(info) = &self.metadata else {
+            return;
+        };
+        let info = info.clone();
+        let peer_id = *self.peer_id.as_bytes();
+        let status_tx = self.status_tx.clone();
+
+        self.inbound.spawn(async move {
+            let addr = inbound.addr;
+            let serving = async {
+                let mut peer = PeerConn::accept(inbound, peer_id).await?;
+                serve_to(&mut peer, &info.raw).await
+            };
+            if let Err(e) = serving.await {
+                status_tx.send_modify(|status| status.set_error(format!("serving {addr}: {e}")));
+            }
+        });
+    }
+
     fn handle_cmd(&mut self, cmd: Command) {
         match cmd {
             Command::Pause => self.worker_state = WorkerState::Paused,
@@ -121,67 +260,587 @@ impl Worker {
         }
     }
 
-    fn build_url(&self) -> String {
+    /// Shows the error in the status, there is nobody to return it to.
+    fn report(&self, error: impl Display) {
+        self.status_tx.send_modify(|status| status.set_error(error));
+    }
+
+    fn build_url(&self, announce: &str, info_hash: &str) -> String {
         let dynamic_params = format!(
             "uploaded={0}&downloaded={1}&left={2}",
             self.uploaded, self.downloaded, self.left
         );
-        format!("{0}&{1}", self.base_url, dynamic_params)
+        // announce urls are allowed to carry their own query
+        let sep = if announce.contains('?') { '&' } else { '?' };
+        format!(
+            "{announce}{sep}info_hash={info_hash}&{0}&{1}",
+            self.query, dynamic_params
+        )
     }
 
     async fn tick(&mut self) {
-        let url = self.build_url();
-        dbg!(&url);
+        if Instant::now() >= self.next_announce {
+            self.announce_all().await;
+        }
+        if let Metadata::Fetching(_) = self.metadata {
+            self.fetch_metadata();
+        }
+        if !self.web_seeds.is_empty() {
+            self.download_from_web_seeds().await;
+        }
+    }
+
+    /// Announces every info hash to the first tracker that answers,
+    /// tier by tier (BEP 12).
+    async fn announce_all(&mut self) {
+        let mut interval = None;
+        for info_hash in self.info_hashes.clone() {
+            if let Some(response) = self.announce_hash(&info_hash).await {
+                interval = Some(
+                    interval.map_or(response.interval, |i: Duration| i.min(response.interval)),
+                );
+                for peer in response.peers {
+                    if !self.peers.contains(&peer) {
+                        self.peers.push(peer);
+                    }
+                }
+            }
+        }
+        // peers that had no metadata may have it by now
+        self.metadata_asked.clear();
+        self.next_announce = Instant::now() + interval.unwrap_or(ANNOUNCE_RETRY);
+    }
 
-        let resp = self.http.get(url).send().await.unwrap();
-        dbg!(resp.status());
-        dbg!(resp.url()); 
-        dbg!(resp.headers().get("content-type"));
-        dbg!(resp.bytes().await.unwrap());
+    async fn announce_hash(&mut self, info_hash: &str) -> Option<AnnounceResponse> {
+        let trackers: Vec<(TrackerPos, String)> = self
+            .tiers
+            .iter()
+            .map(|(pos, url)| (pos, url.to_owned()))
+            .collect();
+
+        for (pos, announce) in trackers {
+            let url = self.build_url(&announce, info_hash);
+
+            match self.announce(url).await {
+                Ok(response) => {
+                    self.tiers.promote(pos);
+                    return Some(response);
+                }
+                // falling through to the next tracker:
+                Err(e) => self.report(format!("announce to {announce}: {e}")),
+            }
+        }
+        None
+    }
+
+    async fn announce(&self, url: String) -> Result<AnnounceResponse, AnnounceError> {
+        let body = self
+            .http
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+        AnnounceResponse::parse(&body)
+    }
+
+    /// Races a few peers for the metadata in the background, so the run
+    /// loop keeps taking commands and inbound peers meanwhile. A round ends
+    /// when all of its peers are done or its deadline passes, the next one
+    /// asks the peers not asked since the last announce.
+    fn fetch_metadata(&mut self) {
+        while let Some(joined) = self.metadata_round.try_join_next() {
+            // aborted along with the worker
+            let Ok((addr, fetched)) = joined else {
+                continue;
+            };
+            match fetched {
+                Ok(info) => {
+                    // drops the rest of the round
+                    self.metadata_round = JoinSet::new();
+                    self.left = info.total_length();
+                    self.metadata = Metadata::Ready(Arc::new(info));
+                    self.status_tx
+                        .send_modify(|status| status.set_fetching_metadata(false));
+                    self.on_metadata();
+                    return;
+                }
+                Err(e) => self.report(format!("metadata from {addr}: {e}")),
+            }
+        }
+
+        let Metadata::Fetching(fetch) = &self.metadata else {
+            return;
+        };
+        if !self.metadata_round.is_empty() {
+            return;
+        }
+
+        let info_hash = fetch.info_hash();
+        let peer_id = *self.peer_id.as_bytes();
+        let deadline = Instant::now() + METADATA_TIMEOUT;
+        let peers: Vec<SocketAddr> = self
+            .peers
+            .iter()
+            .chain(&self.extra_peers)
+            .filter(|addr| !self.metadata_asked.contains(addr))
+            .take(METADATA_PEERS)
+            .copied()
+            .collect();
+        for addr in peers {
+            self.metadata_asked.insert(addr);
+            let mut fetch = fetch.clone();
+            self.metadata_round.spawn(async move {
+                let fetching = async {
+                    let mut peer = PeerConn::connect(addr, info_hash, peer_id).await?;
+                    fetch_from(&mut peer, &mut fetch).await
+                };
+                let fetched = time::timeout_at(deadline, fetching)
+                    .await
+                    .unwrap_or(Err(MetadataError::Peer(PeerError::Timeout)));
+                (addr, fetched)
+            });
+        }
+    }
+
+    /// Sets up what needs the info dict: the private flag and the storage.
+    /// Pieces are checked against v1 hashes, so v2-only torrents get no
+    /// storage for now.
+    fn on_metadata(&mut self) {
+        let Metadata::Ready(info) = &self.metadata else {
+            return;
+        };
+        let private = info.private;
+        self.status_tx
+            .send_modify(|status| status.set_private(private));
+        if private {
+            self.extra_peers.clear();
+        }
+
+        // a magnet may name only one of the hashes of a hybrid torrent
+        let hashes = [
+            info.is_v1().then_some(InfoHash::V1(info.info_hash)),
+            info.info_hash_v2.map(InfoHash::V2),
+        ];
+        for hash in hashes.into_iter().flatten() {
+            let encoded = hash.url_encoded();
+            if self.info_hashes.contains(&encoded) {
+                continue;
+            }
+            self.info_hashes.push(encoded);
+            if let Some(stream_tx) = self.stream_tx.upgrade() {
+                let _ = self
+                    .session_tx
+                    .try_send(SessionEvent::RegisterWorker(hash, stream_tx));
+            }
+        }
+
+        if info.pieces.is_empty() {
+            return;
+        }
+
+        let storage = Storage::new(info, &self.save_to);
+        self.have = vec![false; storage.layout().piece_count()];
+        self.storage = Some(storage);
+    }
+
+    /// Fetches a batch of missing pieces. Each piece is asked from the seed
+    /// that served the last one first, then from the others in turn, the
+    /// batch ends when none of them has it. Seeds that served nothing in a
+    /// batch but failed back off before they are asked again.
+    async fn download_from_web_seeds(&mut self) {
+        let (Metadata::Ready(info), Some(storage)) = (&self.metadata, &self.storage) else {
+            return;
+        };
+        let now = Instant::now();
+        let ready: Vec<usize> = (0..self.web_seeds.len())
+            .filter(|&seed| self.web_seeds[seed].is_ready(now))
+            .collect();
+        if ready.is_empty() {
+            return;
+        }
+        let missing: Vec<usize> = (0..self.have.len())
+            .filter(|&piece| !self.have[piece])
+            .take(WEB_SEED_BATCH)
+            .collect();
+
+        let mut current = 0;
+        let mut served = vec![false; self.web_seeds.len()];
+        let mut failed = vec![false; self.web_seeds.len()];
+        let mut done = Vec::new();
+        for piece in missing {
+            let mut data = None;
+            for i in 0..ready.len() {
+                let turn = (current + i) % ready.len();
+                let seed = ready[turn];
+                let web_seed = &self.web_seeds[seed];
+                match web_seed.fetch_piece(&self.http, info, storage, piece).await {
+                    Ok(piece_data) => {
+                        current = turn;
+                        served[seed] = true;
+                        data = Some(piece_data);
+                        break;
+                    }
+                    // falling through to the next web seed:
+                    Err(e) => {
+                        failed[seed] = true;
+                        self.report(format!("web seed {}: {e}", web_seed.url()));
+                    }
+                }
+            }
+            let Some(data) = data else {
+                break;
+            };
+            if let Err(e) = storage.write_piece(piece, &data) {
+                self.report(format!("writing piece {piece}: {e}"));
+                break;
+            }
+            done.push(piece);
+        }
+
+        for (seed, web_seed) in self.web_seeds.iter_mut().enumerate() {
+            if served[seed] {
+                web_seed.reset_backoff();
+            } else if failed[seed] {
+                web_seed.back_off();
+            }
+        }
+
+        let total = info.total_length() as f64;
+        for piece in done {
+            let len = storage.layout().piece_len(piece);
+            self.have[piece] = true;
+            self.downloaded += len;
+            self.left = self.left.saturating_sub(len);
+            self.status_tx
+                .send_modify(|status| status.update_progress(len as f64 / total));
+        }
+        if self.left == 0 && !self.status_tx.borrow().is_finished {
+            if let Err(e) = storage.finish() {
+                self.report(format!("finishing files: {e}"));
+            }
+            self.tracker_state = TrackerState::Completed;
+            self.status_tx.send_modify(|status| status.finish());
+        }
     }
 }
 
+#[cfg(test)]
 mod tests {
-    use crate::bencode::Torrent;
+    use std::path::Path;
+
+    use crate::{
+        bencode::Torrent, cryptos::hash::make_sha1, magnet::Magnet,
+        sessions::handshake::HandshakeHead,
+    };
 
     use super::*;
 
-    #[tokio::test]
-    #[ignore]
-    async fn tick_real_tracker() {
+    fn worker(tiers: Vec<Vec<String>>) -> Worker {
         let (_cmd_tx, cmd_rx) = tokio::sync::mpsc::channel(1);
         let (status_tx, _status_rx) = tokio::sync::watch::channel(TrackerStatus::default());
-        let (_stream_tx, stream_rx) = tokio::sync::mpsc::channel(1);
+        let (stream_tx, stream_rx) = tokio::sync::mpsc::channel(1);
+        let (session_tx, _session_rx) = tokio::sync::mpsc::channel(1);
 
-        let info_hash =
-            Torrent::from_file("../test_data/fixtures/ubuntu-25.04-desktop-amd64_archive.torrent")
-                .expect("file exists and can be read")
-                .info_hash()
-                .expect("there must be info hash at this point");
-        let info_hash = escape_hash(info_hash);
-
-        let mut worker = Worker {
+        Worker {
             command_rx: cmd_rx,
             status_tx,
             stream_rx,
+            stream_tx: stream_tx.downgrade(),
+            session_tx,
 
-            base_url: format!(
-                "http://bt1.archive.org:6969/announce?info_hash={info_hash}&peer_id=RANDOMTESTPEERID1234&port=51413"
-            ),
+            info_hashes: vec!["HASH".to_string()],
+            query: "peer_id=RANDOMTESTPEERID1234&port=51413".to_string(),
+            tiers: TrackerTiers::new(tiers),
+            next_announce: Instant::now(),
             http: reqwest::Client::new(),
 
+            peer_id: PeerId::new(*b"RANDOMTESTPEERID1234"),
+            peers: Vec::new(),
+            extra_peers: Vec::new(),
+            metadata: Metadata::Ready(Arc::default()),
+            metadata_round: JoinSet::new(),
+            metadata_asked: HashSet::new(),
+            inbound: JoinSet::new(),
+            web_seeds: Vec::new(),
+
+            save_to: PathBuf::new(),
+            storage: None,
+            have: Vec::new(),
+
             uploaded: 0,
             downloaded: 0,
             left: 123456,
 
             worker_state: WorkerState::default(),
             tracker_state: TrackerState::default(),
-        };
+        }
+    }
+
+    #[test]
+    fn build_url_keeps_tracker_query() {
+        let worker = worker(Vec::new());
+
+        assert_eq!(
+            worker.build_url("http://t/announce", "HASH"),
+            "http://t/announce?info_hash=HASH&peer_id=RANDOMTESTPEERID1234&port=51413&uploaded=0&downloaded=0&left=123456"
+        );
+        assert_eq!(
+            worker.build_url("http://t/announce?key=1", "HASH"),
+            "http://t/announce?key=1&info_hash=HASH&peer_id=RANDOMTESTPEERID1234&port=51413&uploaded=0&downloaded=0&left=123456"
+        );
+    }
+
+    #[tokio::test]
+    async fn tick_falls_through_to_working_tracker() {
+        let server = wiremock::MockServer::start().await;
+        wiremock::Mock::given(wiremock::matchers::path("/announce"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_bytes("d8:intervali1800ee"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let dead = format!("{}/dead", server.uri());
+        let alive = format!("{}/announce", server.uri());
+        let mut worker = worker(vec![vec![dead.clone()], vec![dead.clone(), alive.clone()]]);
 
         worker.tick().await;
+
+        let order: Vec<&str> = worker.tiers.iter().map(|(_, url)| url).collect();
+        assert_eq!(order[1], alive);
+        let error = worker.status_tx.borrow().last_error.clone().unwrap();
+        assert!(error.starts_with(&format!("announce to {dead}: ")));
     }
-}
 
-fn escape_hash(hash: [u8; 20]) -> String {
-    form_urlencoded::byte_serialize(&hash[..]).collect()
+    #[tokio::test]
+    async fn tick_announces_every_hash() {
+        let server = wiremock::MockServer::start().await;
+        for hash in ["V1", "V2"] {
+            wiremock::Mock::given(wiremock::matchers::query_param("info_hash", hash))
+                .respond_with(wiremock::ResponseTemplate::new(200))
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+
+        let mut worker = worker(vec![vec![format!("{}/announce", server.uri())]]);
+        worker.info_hashes = vec!["V1".to_string(), "V2".to_string()];
+
+        worker.tick().await;
+    }
+
+    #[tokio::test]
+    async fn metadata_errors_are_reported() {
+        // nothing listens there anymore
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+
+        let magnet = Magnet::parse(&format!("magnet:?xt=urn:btih:{}", "ab".repeat(20))).unwrap();
+        let mut worker = worker(Vec::new());
+        worker.metadata = Metadata::Fetching(MetadataFetch::new(&magnet));
+        worker.extra_peers = vec![addr];
+
+        worker.fetch_metadata();
+        time::timeout(Duration::from_secs(5), async {
+            while worker.status_tx.borrow().last_error.is_none() {
+                time::sleep(Duration::from_millis(20)).await;
+                worker.fetch_metadata();
+            }
+        })
+        .await
+        .expect("the refused peer is reported");
+
+        let error = worker.status_tx.borrow().last_error.clone().unwrap();
+        assert!(error.starts_with(&format!("metadata from {addr}: ")));
+    }
+
+    #[tokio::test]
+    async fn metadata_is_fetched_in_rounds() {
+        let magnet = Magnet::parse(&format!("magnet:?xt=urn:btih:{}", "ab".repeat(20))).unwrap();
+        let mut worker = worker(Vec::new());
+        worker.metadata = Metadata::Fetching(MetadataFetch::new(&magnet));
+        // documentation addresses, nobody answers there
+        worker.extra_peers = (1..=6)
+            .map(|i| format!("192.0.2.{i}:6881").parse().unwrap())
+            .collect();
+
+        worker.fetch_metadata();
+        assert_eq!(worker.metadata_round.len(), METADATA_PEERS);
+
+        // the round is still running, no other peers are asked meanwhile
+        worker.fetch_metadata();
+        assert_eq!(worker.metadata_asked.len(), METADATA_PEERS);
+    }
+
+    #[test]
+    fn hashes_from_the_metadata_are_routed() {
+        let (stream_tx, _stream_rx) = mpsc::channel(1);
+        let (session_tx, mut session_rx) = mpsc::channel(1);
+        let mut worker = worker(Vec::new());
+        worker.stream_tx = stream_tx.downgrade();
+        worker.session_tx = session_tx;
+        worker.metadata = Metadata::Ready(Arc::new(Info {
+            info_hash_v2: Some([2; 32]),
+            ..Default::default()
+        }));
+
+        worker.on_metadata();
+
+        let v2 = InfoHash::V2([2; 32]);
+        assert_eq!(worker.info_hashes, ["HASH".to_string(), v2.url_encoded()]);
+        assert!(matches!(
+            session_rx.try_recv(),
+            Ok(SessionEvent::RegisterWorker(hash, _)) if hash == v2
+        ));
+    }
+
+    #[tokio::test]
+    async fn serving_errors_are_reported() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut client = tokio::net::TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (stream, addr) = listener.accept().await.unwrap();
+        tokio::io::AsyncWriteExt::write_all(&mut client, &[0; 20])
+            .await
+            .unwrap();
+
+        let mut worker = worker(Vec::new());
+        // no extension bit, so there is no metadata to serve it
+        let head = HandshakeHead {
+            reserved: [0; 8],
+            info_hash: [0; 20],
+        };
+        worker.accept_peer(InboundPeer { stream, addr, head });
+        while worker.inbound.join_next().await.is_some() {}
+
+        let error = worker.status_tx.borrow().last_error.clone().unwrap();
+        assert!(error.starts_with(&format!("serving {addr}: ")));
+    }
+
+    /// A single file of "abcdefgh" in two pieces, downloaded from `seeds`.
+    fn web_seed_worker(seeds: &[String], save_to: &Path) -> Worker {
+        let pieces = [&b"abcd"[..], b"efgh"].map(make_sha1).concat();
+        let data = [
+            &b"d8:announce8:http://t4:infod6:lengthi8e4:name1:f12:piece lengthi4e6:pieces40:"[..],
+            &pieces,
+            b"ee",
+        ]
+        .concat();
+
+        let mut worker = worker(Vec::new());
+        worker.metadata = Metadata::Ready(Arc::new(Torrent::from_bytes(&data).unwrap().info));
+        worker.web_seeds = seeds.iter().map(|url| WebSeed::new(url).unwrap()).collect();
+        worker.save_to = save_to.to_owned();
+        worker.left = 8;
+        worker.on_metadata();
+        worker
+    }
+
+    #[tokio::test]
+    async fn web_seed_errors_are_reported() {
+        // answers 404 to everything
+        let server = wiremock::MockServer::start().await;
+        let save_to = tempfile::tempdir().unwrap();
+        let seed = format!("{}/f", server.uri());
+        let mut worker = web_seed_worker(std::slice::from_ref(&seed), save_to.path());
+
+        worker.download_from_web_seeds().await;
+
+        assert_eq!(worker.have, [false, false]);
+        let error = worker.status_tx.borrow().last_error.clone().unwrap();
+        assert!(error.starts_with(&format!("web seed {seed}: ")));
+    }
+
+    #[tokio::test]
+    async fn failing_web_seeds_back_off() {
+        use wiremock::{Mock, ResponseTemplate, matchers::any};
+
+        let server = wiremock::MockServer::start().await;
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(404))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let save_to = tempfile::tempdir().unwrap();
+        let seed = format!("{}/f", server.uri());
+        let mut worker = web_seed_worker(std::slice::from_ref(&seed), save_to.path());
+
+        worker.download_from_web_seeds().await;
+        worker.status_tx.send_replace(Default::default());
+        worker.download_from_web_seeds().await;
+
+        assert!(!worker.web_seeds[0].is_ready(Instant::now()));
+        assert_eq!(worker.status_tx.borrow().last_error, None);
+    }
+
+    #[tokio::test]
+    async fn web_seeds_take_turns() {
+        use wiremock::{
+            Mock, ResponseTemplate,
+            matchers::{header, path},
+        };
+
+        // each seed is missing a different piece
+        let server = wiremock::MockServer::start().await;
+        for (seed, range, body) in [("/a", "bytes=4-7", "efgh"), ("/b", "bytes=0-3", "abcd")] {
+            Mock::given(path(seed))
+                .and(header("range", range))
+                .respond_with(ResponseTemplate::new(206).set_body_string(body))
+                .mount(&server)
+                .await;
+        }
+        let save_to = tempfile::tempdir().unwrap();
+        let seeds = [format!("{}/a", server.uri()), format!("{}/b", server.uri())];
+        let mut worker = web_seed_worker(&seeds, save_to.path());
+
+        worker.download_from_web_seeds().await;
+
+        assert_eq!(worker.have, [true, true]);
+        assert_eq!(worker.left, 0);
+        assert!(worker.status_tx.borrow().is_finished);
+        assert_eq!(
+            std::fs::read(save_to.path().join("f")).unwrap(),
+            b"abcdefgh"
+        );
+    }
+
+    #[test]
+    fn private_torrents_drop_extra_peers() {
+        for (private, kept) in [("0", 1), ("1", 0)] {
+            let data = format!(
+                "d8:announce8:http://t4:infod6:lengthi1e4:name1:f12:piece lengthi16384e\
+                 6:pieces20:aaaaaaaaaaaaaaaaaaaa7:privatei{private}eee"
+            );
+            let info = Torrent::from_bytes(data.as_bytes()).unwrap().info;
+
+            let mut worker = worker(Vec::new());
+            worker.metadata = Metadata::Ready(Arc::new(info));
+            worker.extra_peers = vec!["127.0.0.1:6881".parse().unwrap()];
+            worker.on_metadata();
+
+            assert_eq!(worker.extra_peers.len(), kept);
+            assert_eq!(worker.status_tx.borrow().private, private == "1");
+        }
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn tick_real_tracker() {
+        let info_hash =
+            Torrent::from_file("../test_data/fixtures/ubuntu-25.04-desktop-amd64_archive.torrent")
+                .expect("file exists and can be read")
+                .info_hash()
+                .expect("there must be info hash at this point");
+        let info_hash = info_hash.url_encoded();
+
+        let mut worker = worker(vec![vec![
+            "http://bt1.archive.org:6969/announce".to_string(),
+        ]]);
+        worker.info_hashes = vec![info_hash];
+
+        worker.tick().await;
+    }
 }